@@ -1,7 +1,5 @@
 mod perf;
 
-use std::time::Duration;
-
 use advent_of_code_2021::puzzles::{day04, day05, day07, day15};
 use criterion::{criterion_group, criterion_main, Criterion};
 
@@ -19,7 +17,6 @@ fn day05(c: &mut Criterion) {
 
 fn day07(c: &mut Criterion) {
     c.benchmark_group("Day 07")
-        .measurement_time(Duration::from_secs_f32(7.5))
         .bench_function("Part One", |b| b.iter(|| day07::part_one()))
         .bench_function("Part Two", |b| b.iter(|| day07::part_two()));
 }
@@ -1,6 +1,7 @@
 mod shared {
     pub mod input;
     pub mod grid;
+    pub mod matrix;
 }
 mod prelude;
 
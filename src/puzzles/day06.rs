@@ -1,9 +1,53 @@
 // Day 6: Lanternfish
+pub mod model;
+
 use crate::prelude::*;
+use model::PopulationModel;
+use num_bigint::BigUint;
+
+/// How many days a fish takes to spawn another one,
+/// and how many extra days a newborn fish takes to spawn its first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifeCycle {
+    cycle_length: usize,
+    maturity: usize,
+}
 
-const CYCLE_LENGTH: u8 = 7;
-const MATURITY: u8 = 2;
-const TOTAL_LENGTH: usize = (CYCLE_LENGTH + MATURITY) as usize;
+pub const LANTERNFISH: LifeCycle = LifeCycle {
+    cycle_length: 7,
+    maturity: 2,
+};
+
+impl LifeCycle {
+    pub fn new(cycle_length: usize, maturity: usize) -> Result<Self, String> {
+        if cycle_length == 0 {
+            return Err("Fish need at least a day between spawning".to_string());
+        }
+        Ok(LifeCycle {
+            cycle_length,
+            maturity,
+        })
+    }
+
+    fn total_length(&self) -> usize {
+        self.cycle_length + self.maturity
+    }
+
+    fn count_fish_by_days_until_spawn(&self, list: &[u8]) -> Result<Vec<usize>, String> {
+        let mut fish_by_days_until_spawn = vec![0; self.total_length()];
+        for &fish_state in list {
+            if fish_state as usize >= self.total_length() {
+                return Err(format!(
+                    "Maximum value is {}, but received a {}",
+                    self.total_length(),
+                    fish_state
+                ));
+            }
+            fish_by_days_until_spawn[fish_state as usize] += 1;
+        }
+        Ok(fish_by_days_until_spawn)
+    }
+}
 
 lazy_static! {
     static ref PUZZLE_INPUT: Box<[u8]> = include_str!("day06_input.txt")
@@ -13,45 +57,38 @@ lazy_static! {
 }
 
 pub fn part_one() -> Result<usize, String> {
-    let mut all_fish = FishSimulation::from_fish_list(&PUZZLE_INPUT)?;
+    let mut all_fish = FishSimulation::from_fish_list(&PUZZLE_INPUT, LANTERNFISH)?;
     Ok(all_fish.count_fish_after_days(80))
 }
 
-pub fn part_two() -> Result<usize, String> {
-    let mut all_fish = FishSimulation::from_fish_list(&PUZZLE_INPUT)?;
-    Ok(all_fish.count_fish_after_days(256))
+pub fn part_two() -> Result<BigUint, String> {
+    let model = PopulationModel::from_fish_list(&PUZZLE_INPUT, LANTERNFISH)?;
+    Ok(model.count_fish_after_days(256))
 }
 
 struct FishSimulation {
-    fish_by_days_until_spawn: [usize; TOTAL_LENGTH],
+    life_cycle: LifeCycle,
+    fish_by_days_until_spawn: Vec<usize>,
 }
 
 impl FishSimulation {
-    fn from_fish_list(list: &[u8]) -> Result<Self, String> {
-        let mut fish_by_days_until_spawn = [0; TOTAL_LENGTH];
-        for &fish_state in list {
-            if fish_state >= TOTAL_LENGTH as u8 {
-                return Err(format!(
-                    "Maximum value is {}, but received a {}",
-                    TOTAL_LENGTH, fish_state
-                ));
-            }
-            fish_by_days_until_spawn[fish_state as usize] += 1;
-        }
+    fn from_fish_list(list: &[u8], life_cycle: LifeCycle) -> Result<Self, String> {
         Ok(FishSimulation {
-            fish_by_days_until_spawn,
+            life_cycle,
+            fish_by_days_until_spawn: life_cycle.count_fish_by_days_until_spawn(list)?,
         })
     }
 
     fn tick(&mut self) {
+        let total_length = self.life_cycle.total_length();
         let fish_to_spawn = self.fish_by_days_until_spawn[0];
-        for i in 1..(TOTAL_LENGTH) {
+        for i in 1..total_length {
             self.fish_by_days_until_spawn[i - 1] = self.fish_by_days_until_spawn[i];
         }
-        // reset cycle
-        self.fish_by_days_until_spawn[CYCLE_LENGTH as usize - 1] += fish_to_spawn;
         // add new fish
-        self.fish_by_days_until_spawn[TOTAL_LENGTH - 1] = fish_to_spawn;
+        self.fish_by_days_until_spawn[total_length - 1] = fish_to_spawn;
+        // reset cycle (after adding new fish, in case they start out mature)
+        self.fish_by_days_until_spawn[self.life_cycle.cycle_length - 1] += fish_to_spawn;
     }
 
     fn count_fish(&self) -> usize {
@@ -77,7 +114,7 @@ mod tests {
 
     #[test]
     fn part_one_example() {
-        let mut all_fish = FishSimulation::from_fish_list(&EXAMPLE_INPUT).unwrap();
+        let mut all_fish = FishSimulation::from_fish_list(&EXAMPLE_INPUT, LANTERNFISH).unwrap();
         assert_eq!(all_fish.count_fish_after_days(18), 26);
         assert_eq!(all_fish.count_fish_after_days(80 - 18), 5934);
     }
@@ -90,13 +127,13 @@ mod tests {
 
     #[test]
     fn part_two_example() {
-        let mut all_fish = FishSimulation::from_fish_list(&EXAMPLE_INPUT).unwrap();
+        let mut all_fish = FishSimulation::from_fish_list(&EXAMPLE_INPUT, LANTERNFISH).unwrap();
         assert_eq!(all_fish.count_fish_after_days(256), 26984457539);
     }
 
     #[test]
     fn part_two_answer() {
         let result = part_two();
-        assert_eq!(result, Ok(1595330616005));
+        assert_eq!(result, Ok(BigUint::from(1595330616005u64)));
     }
 }
@@ -0,0 +1,140 @@
+use super::LifeCycle;
+use crate::shared::matrix::Matrix;
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+/// Fish counts that can jump straight to any day.
+///
+/// Every fish alive `total_length` days ago spawns exactly once in the last `cycle_length` days,
+/// and nothing younger has spawned yet, so the population follows
+/// `P(n) = P(n - cycle_length) + P(n - total_length)`.
+pub struct PopulationModel {
+    life_cycle: LifeCycle,
+    fish_by_days_until_spawn: Vec<BigUint>,
+    /// The population for the first `total_length` days, which the recurrence starts from
+    first_days: Vec<BigUint>,
+}
+
+impl PopulationModel {
+    pub fn from_fish_list(list: &[u8], life_cycle: LifeCycle) -> Result<Self, String> {
+        let fish_by_days_until_spawn = life_cycle
+            .count_fish_by_days_until_spawn(list)?
+            .into_iter()
+            .map(BigUint::from)
+            .collect();
+        let mut model = PopulationModel {
+            life_cycle,
+            fish_by_days_until_spawn,
+            first_days: vec![],
+        };
+        model.first_days = model.population_series(life_cycle.total_length() - 1);
+        Ok(model)
+    }
+
+    /// The matrix that turns the populations for `total_length` days in a row
+    /// into the same days, one day later
+    fn companion_matrix(&self) -> Matrix<BigUint> {
+        let size = self.life_cycle.total_length();
+        let mut matrix = vec![vec![BigUint::zero(); size]; size];
+        for (row, cells) in matrix.iter_mut().enumerate().take(size - 1) {
+            cells[row + 1] = 1u8.into();
+        }
+        matrix[size - 1][0] += 1u8;
+        matrix[size - 1][self.life_cycle.maturity] += 1u8;
+        Matrix(matrix)
+    }
+
+    pub fn count_fish_after_days(&self, days: u64) -> BigUint {
+        if let Some(count) = usize::try_from(days)
+            .ok()
+            .and_then(|days| self.first_days.get(days))
+        {
+            return count.clone();
+        }
+        let populations = self
+            .companion_matrix()
+            .checked_pow(days)
+            .and_then(|matrix| matrix.checked_apply(&self.first_days))
+            .expect("BigUint arithmetic can't overflow");
+        populations[0].clone()
+    }
+
+    /// The population on every day up to `days`, worked out one day at a time, for charting
+    pub fn population_series(&self, days: usize) -> Vec<BigUint> {
+        let total_length = self.life_cycle.total_length();
+        let mut fish_by_days_until_spawn = self.fish_by_days_until_spawn.clone();
+        let mut series = Vec::with_capacity(days + 1);
+        series.push(fish_by_days_until_spawn.iter().sum());
+        for _ in 0..days {
+            let fish_to_spawn = fish_by_days_until_spawn[0].clone();
+            fish_by_days_until_spawn.rotate_left(1);
+            fish_by_days_until_spawn[total_length - 1] = fish_to_spawn.clone();
+            fish_by_days_until_spawn[self.life_cycle.cycle_length - 1] += fish_to_spawn;
+            series.push(fish_by_days_until_spawn.iter().sum());
+        }
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{FishSimulation, LANTERNFISH};
+    use super::*;
+
+    const EXAMPLE_INPUT: [u8; 5] = [3, 4, 3, 1, 2];
+
+    #[test]
+    fn test_population_series() {
+        let model = PopulationModel::from_fish_list(&EXAMPLE_INPUT, LANTERNFISH).unwrap();
+        let expected: Vec<BigUint> = [5u8, 5, 6, 7, 9, 10]
+            .into_iter()
+            .map(BigUint::from)
+            .collect();
+        assert_eq!(model.population_series(5), expected);
+    }
+
+    #[test]
+    fn test_matches_simulation() {
+        let life_cycles = [
+            LANTERNFISH,
+            LifeCycle::new(3, 1).unwrap(),
+            LifeCycle::new(5, 0).unwrap(),
+            LifeCycle::new(1, 4).unwrap(),
+        ];
+        for life_cycle in life_cycles {
+            let list = [0, 1, 2, 2, 3];
+            let model = PopulationModel::from_fish_list(&list, life_cycle).unwrap();
+            let mut simulation = FishSimulation::from_fish_list(&list, life_cycle).unwrap();
+            for days in 0..60 {
+                assert_eq!(
+                    model.count_fish_after_days(days),
+                    BigUint::from(simulation.count_fish()),
+                    "{:?} after {} days",
+                    life_cycle,
+                    days
+                );
+                simulation.tick();
+            }
+        }
+    }
+
+    #[test]
+    fn test_far_future() {
+        let model = PopulationModel::from_fish_list(&EXAMPLE_INPUT, LANTERNFISH).unwrap();
+        assert_eq!(
+            model.count_fish_after_days(256),
+            BigUint::from(26984457539u64)
+        );
+        let series = model.population_series(5000);
+        assert_eq!(model.count_fish_after_days(5000), series[5000]);
+    }
+
+    #[test]
+    fn test_invalid_fish() {
+        assert!(PopulationModel::from_fish_list(&[9], LANTERNFISH).is_err());
+        assert_eq!(
+            LifeCycle::new(0, 2),
+            Err("Fish need at least a day between spawning".to_string())
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use std::ops::RangeInclusive;
-
 // Day 7: The Treachery of Whales
 use crate::prelude::*;
+use alignment::{cheapest_alignments, Linear, OptimalAlignment, Triangular};
+
+pub mod alignment;
+pub mod weighted;
 
 lazy_static! {
     static ref PUZZLE_INPUT: Box<[isize]> = include_str!("day07_input.txt")
@@ -25,55 +27,24 @@ struct Alignment {
     position: isize,
 }
 
-// Panics if an empty list is provided
-fn cheapest_alignment(crabs: &[isize]) -> Alignment {
-    let min = *crabs.iter().min().unwrap();
-    let max = *crabs.iter().max().unwrap();
-    RangeInclusive::new(min, max)
-        .map(|position| Alignment {
-            fuel_required: fuel_to_move_to_position(crabs, position),
-            position,
-        })
-        .min_by_key(|alignment| alignment.fuel_required)
-        .unwrap()
+impl From<OptimalAlignment> for Alignment {
+    /// Picks the leftmost of the cheapest positions
+    fn from(optimal: OptimalAlignment) -> Self {
+        Alignment {
+            fuel_required: optimal.fuel_required,
+            position: *optimal.positions[0].start(),
+        }
+    }
 }
 
-fn fuel_to_move_to_position(crabs: &[isize], position: isize) -> usize {
-    crabs
-        .into_iter()
-        .map(|&crab| (position - crab).abs() as usize)
-        .sum()
+// Panics if an empty list is provided
+fn cheapest_alignment(crabs: &[isize]) -> Alignment {
+    cheapest_alignments(crabs, &Linear).unwrap().into()
 }
 
+// Panics if an empty list is provided
 fn cheapest_alignment_mk2(crabs: &[isize]) -> Alignment {
-    let min = *crabs.iter().min().unwrap();
-    let max = *crabs.iter().max().unwrap();
-
-    let fuel_costs = {
-        let greatest_distance = (max - min).abs() as usize;
-        let mut fuel_costs: Box<[usize]> = RangeInclusive::new(0, greatest_distance)
-            .map(|_| 0)
-            .collect();
-        for i in RangeInclusive::new(1, greatest_distance) {
-            fuel_costs[i] = fuel_costs[i - 1] + i;
-        }
-        fuel_costs
-    };
-
-    RangeInclusive::new(min, max)
-        .map(|position| Alignment {
-            fuel_required: fuel_to_move_to_position_mk2(crabs, &fuel_costs, position),
-            position,
-        })
-        .min_by_key(|alignment| alignment.fuel_required)
-        .unwrap()
-}
-
-fn fuel_to_move_to_position_mk2(crabs: &[isize], fuel_costs: &[usize], position: isize) -> usize {
-    crabs
-        .into_iter()
-        .map(|&crab| fuel_costs[(position - crab).abs() as usize])
-        .sum()
+    cheapest_alignments(crabs, &Triangular).unwrap().into()
 }
 
 #[cfg(test)]
@@ -0,0 +1,351 @@
+use crate::prelude::*;
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
+
+/// How much fuel a crab burns to move some distance
+pub trait CostFunction {
+    fn cost(&self, distance: usize) -> usize;
+
+    /// How to find the cheapest positions for crabs that are at most `max_distance` apart.
+    /// Unless a cost function knows better, a ternary search is used if the cost never grows
+    /// more slowly as the distance increases, and every position is tried if it does.
+    fn strategy(&self, max_distance: usize) -> Strategy {
+        let mut previous_step = 0;
+        for distance in 0..max_distance {
+            let step = match self.cost(distance + 1).checked_sub(self.cost(distance)) {
+                Some(step) => step,
+                None => return Strategy::Exhaustive,
+            };
+            if step < previous_step {
+                return Strategy::Exhaustive;
+            }
+            previous_step = step;
+        }
+        Strategy::TernarySearch
+    }
+
+    /// The first distance below `max_distance` where moving a step further costs less,
+    /// if there is one. Unless a cost function knows better, every distance is checked.
+    fn first_decrease(&self, max_distance: usize) -> Option<usize> {
+        (0..max_distance).find(|&distance| self.cost(distance + 1) < self.cost(distance))
+    }
+}
+
+/// A unit of fuel per step, like in part one
+pub struct Linear;
+
+/// One more unit of fuel for each step than the step before, like in part two
+pub struct Triangular;
+
+/// The square of the distance
+pub struct Quadratic;
+
+/// Any other cost function
+pub struct Custom<F: Fn(usize) -> usize>(pub F);
+
+impl CostFunction for Linear {
+    fn cost(&self, distance: usize) -> usize {
+        distance
+    }
+
+    fn strategy(&self, _max_distance: usize) -> Strategy {
+        Strategy::Median
+    }
+
+    fn first_decrease(&self, _max_distance: usize) -> Option<usize> {
+        None
+    }
+}
+
+impl CostFunction for Triangular {
+    fn cost(&self, distance: usize) -> usize {
+        distance * (distance + 1) / 2
+    }
+
+    fn strategy(&self, _max_distance: usize) -> Strategy {
+        Strategy::MeanNeighborhood
+    }
+
+    fn first_decrease(&self, _max_distance: usize) -> Option<usize> {
+        None
+    }
+}
+
+impl CostFunction for Quadratic {
+    fn cost(&self, distance: usize) -> usize {
+        distance * distance
+    }
+
+    fn strategy(&self, _max_distance: usize) -> Strategy {
+        Strategy::MeanNeighborhood
+    }
+
+    fn first_decrease(&self, _max_distance: usize) -> Option<usize> {
+        None
+    }
+}
+
+impl<F: Fn(usize) -> usize> CostFunction for Custom<F> {
+    fn cost(&self, distance: usize) -> usize {
+        (self.0)(distance)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Strategy {
+    /// Linear costs are cheapest at the median
+    Median,
+    /// Quadratic and triangular costs are cheapest within a step of the mean
+    MeanNeighborhood,
+    /// Narrows down the cheapest position of any convex cost function
+    TernarySearch,
+    /// Tries every position between the outermost crabs
+    Exhaustive,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OptimalAlignment {
+    pub fuel_required: usize,
+    /// Every position that takes that little fuel, in order, as runs of neighbouring positions
+    /// so that crabs that are far apart don't have to list everywhere in between
+    pub positions: Vec<RangeInclusive<isize>>,
+}
+
+pub fn total_cost(crabs: &[isize], cost: &impl CostFunction, position: isize) -> usize {
+    crabs
+        .iter()
+        .map(|&crab| cost.cost((position - crab).unsigned_abs()))
+        .sum()
+}
+
+/// `None` if there aren't any crabs
+pub fn cheapest_alignments(crabs: &[isize], cost: &impl CostFunction) -> Option<OptimalAlignment> {
+    let (&min, &max) = (crabs.iter().min()?, crabs.iter().max()?);
+    let strategy = cost.strategy((max - min).unsigned_abs());
+    cheapest_alignments_with(crabs, cost, strategy)
+}
+
+/// Like `cheapest_alignments`, but with a particular strategy, which has to suit the cost function
+pub fn cheapest_alignments_with(
+    crabs: &[isize],
+    cost: &impl CostFunction,
+    strategy: Strategy,
+) -> Option<OptimalAlignment> {
+    let (&min, &max) = (crabs.iter().min()?, crabs.iter().max()?);
+    let total = |position: isize| total_cost(crabs, cost, position);
+
+    let candidates = match strategy {
+        Strategy::Exhaustive => {
+            let mut fuel_required = usize::MAX;
+            let mut positions = vec![];
+            for position in min..=max {
+                let fuel = total(position);
+                if fuel < fuel_required {
+                    fuel_required = fuel;
+                    positions.clear();
+                }
+                if fuel == fuel_required {
+                    add_to_runs(&mut positions, position);
+                }
+            }
+            return Some(OptimalAlignment {
+                fuel_required,
+                positions,
+            });
+        }
+        Strategy::Median => {
+            let sorted = crabs.iter().copied().sorted().collect_vec();
+            vec![sorted[(sorted.len() - 1) / 2]]
+        }
+        Strategy::MeanNeighborhood => {
+            let sum: isize = crabs.iter().sum();
+            let count = crabs.len() as isize;
+            let floor = sum.div_euclid(count);
+            ((floor - 1)..=(floor + 2)).collect()
+        }
+        Strategy::TernarySearch => ternary_search(min, max, total).collect(),
+    };
+
+    let (best, fuel_required) = candidates
+        .into_iter()
+        .filter(|position| (min..=max).contains(position))
+        .map(|position| (position, total(position)))
+        .min_by_key(|&(position, fuel)| (fuel, position))?;
+
+    Some(OptimalAlignment {
+        fuel_required,
+        positions: vec![cheapest_run(min, max, best, fuel_required, total)],
+    })
+}
+
+/// Adds a position to the end of some runs, which have to be in order
+pub(super) fn add_to_runs(runs: &mut Vec<RangeInclusive<isize>>, position: isize) {
+    match runs.last_mut() {
+        Some(run) if *run.end() + 1 == position => *run = *run.start()..=position,
+        _ => runs.push(position..=position),
+    }
+}
+
+/// Narrows `low..=high` down to a few positions that include the cheapest one,
+/// for a convex `total`
+pub(super) fn ternary_search(
+    mut low: isize,
+    mut high: isize,
+    total: impl Fn(isize) -> usize,
+) -> RangeInclusive<isize> {
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        let (a, b) = (low + third, high - third);
+        match total(a).cmp(&total(b)) {
+            Ordering::Less => high = b - 1,
+            Ordering::Greater => low = a + 1,
+            Ordering::Equal => {
+                low = a;
+                high = b;
+            }
+        }
+    }
+    low..=high
+}
+
+/// Every position in `low..=high` that's as cheap as `best`. For a convex `total`,
+/// they're all right next to it, so this only has to find where they start and end.
+pub(super) fn cheapest_run(
+    low: isize,
+    high: isize,
+    best: isize,
+    fuel_required: usize,
+    total: impl Fn(isize) -> usize,
+) -> RangeInclusive<isize> {
+    let first = partition_point(low, best, |position| total(position) > fuel_required);
+    let last = partition_point(best, high + 1, |position| total(position) == fuel_required) - 1;
+    first..=last
+}
+
+/// The first position in `low..high` where `predicate` stops being true,
+/// for a predicate that's true up to some point and false from then on
+fn partition_point(mut low: isize, mut high: isize, predicate: impl Fn(isize) -> bool) -> isize {
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if predicate(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_INPUT: [isize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    fn check_every_strategy(crabs: &[isize], cost: &impl CostFunction, strategies: &[Strategy]) {
+        let expected = cheapest_alignments_with(crabs, cost, Strategy::Exhaustive);
+        for &strategy in strategies {
+            assert_eq!(
+                cheapest_alignments_with(crabs, cost, strategy),
+                expected,
+                "{:?} on {:?}",
+                strategy,
+                crabs
+            );
+        }
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(
+            cheapest_alignments(&EXAMPLE_INPUT, &Linear),
+            Some(OptimalAlignment {
+                fuel_required: 37,
+                positions: vec![2..=2]
+            })
+        );
+        assert_eq!(
+            cheapest_alignments(&EXAMPLE_INPUT, &Triangular),
+            Some(OptimalAlignment {
+                fuel_required: 168,
+                positions: vec![5..=5]
+            })
+        );
+    }
+
+    #[test]
+    fn test_several_optimal_positions() {
+        // anywhere between the two middle crabs is just as good
+        assert_eq!(
+            cheapest_alignments(&[0, 3, 7, 20], &Linear),
+            Some(OptimalAlignment {
+                fuel_required: 24,
+                positions: vec![3..=7]
+            })
+        );
+        assert_eq!(
+            cheapest_alignments(&[0, 1], &Quadratic),
+            Some(OptimalAlignment {
+                fuel_required: 1,
+                positions: vec![0..=1]
+            })
+        );
+    }
+
+    #[test]
+    fn test_far_apart() {
+        let far = 1_000_000_000_000;
+        assert_eq!(
+            cheapest_alignments(&[0, far], &Linear),
+            Some(OptimalAlignment {
+                fuel_required: far as usize,
+                positions: vec![0..=far]
+            })
+        );
+        assert_eq!(
+            cheapest_alignments(&[0, far, far], &Linear).map(|it| it.positions),
+            Some(vec![far..=far])
+        );
+    }
+
+    #[test]
+    fn test_strategies_agree() {
+        let inputs: [&[isize]; 4] = [
+            &EXAMPLE_INPUT,
+            &[0, 3, 7, 20],
+            &[-5, 100, 101, 102, 40],
+            &[9, 9, 9],
+        ];
+        let convex = [
+            Strategy::Median,
+            Strategy::MeanNeighborhood,
+            Strategy::TernarySearch,
+        ];
+        for crabs in inputs {
+            check_every_strategy(crabs, &Linear, &[Strategy::Median, Strategy::TernarySearch]);
+            check_every_strategy(crabs, &Triangular, &convex[1..]);
+            check_every_strategy(crabs, &Quadratic, &convex[1..]);
+            check_every_strategy(crabs, &Custom(|d| d * d * d), &[Strategy::TernarySearch]);
+        }
+    }
+
+    #[test]
+    fn test_custom_strategy() {
+        assert_eq!(Custom(|d| 3 * d + 1).strategy(10), Strategy::TernarySearch);
+        // moving further gets cheaper per step, so the cheapest positions can be spread out
+        let concave = Custom(|d| (d as f64).sqrt() as usize);
+        assert_eq!(concave.strategy(10), Strategy::Exhaustive);
+        assert_eq!(
+            cheapest_alignments(&[0, 10, 11], &concave),
+            Some(OptimalAlignment {
+                fuel_required: 4,
+                positions: vec![8..=8, 10..=11]
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_crabs() {
+        assert_eq!(cheapest_alignments(&[], &Linear), None);
+    }
+}
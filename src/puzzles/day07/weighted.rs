@@ -0,0 +1,542 @@
+use super::alignment::{
+    add_to_runs, cheapest_run, ternary_search, CostFunction, OptimalAlignment, Strategy,
+};
+use crate::prelude::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Crab {
+    pub position: isize,
+    /// How much fuel the crab burns for every unit of cost; less efficient crabs weigh more
+    pub weight: usize,
+}
+
+impl Crab {
+    pub fn new(position: isize, weight: usize) -> Self {
+        Crab { position, weight }
+    }
+}
+
+/// `None` if the total doesn't fit in a `usize`
+pub fn weighted_total_cost(
+    crabs: &[Crab],
+    cost: &impl CostFunction,
+    position: isize,
+) -> Option<usize> {
+    crabs.iter().try_fold(0usize, |sum, crab| {
+        let fuel = crab
+            .weight
+            .checked_mul(cost.cost(position.abs_diff(crab.position)))?;
+        sum.checked_add(fuel)
+    })
+}
+
+fn too_much_fuel() -> String {
+    "The fuel required doesn't fit in a usize".to_string()
+}
+
+/// How far apart the outermost crabs and meeting points are
+fn max_distance(crabs: &[Crab], allowed: Option<&[isize]>) -> usize {
+    crabs
+        .iter()
+        .map(|crab| crab.position)
+        .chain(allowed.into_iter().flatten().copied())
+        .minmax()
+        .into_option()
+        .map_or(0, |(min, max)| max.abs_diff(min))
+}
+
+/// Fails if there aren't any crabs, or anywhere they're allowed to meet
+pub fn cheapest_weighted_alignment(
+    crabs: &[Crab],
+    cost: &impl CostFunction,
+    allowed: Option<&[isize]>,
+) -> Result<OptimalAlignment, String> {
+    let allowed = allowed.map(|allowed| allowed.iter().copied().sorted().dedup().collect_vec());
+    let strategy = cost.strategy(max_distance(crabs, allowed.as_deref()));
+    cheapest_with(crabs, cost, allowed.as_deref(), strategy)
+}
+
+/// Like `cheapest_weighted_alignment`, but with `allowed` already sorted and deduplicated,
+/// and a strategy that suits the cost function
+fn cheapest_with(
+    crabs: &[Crab],
+    cost: &impl CostFunction,
+    allowed: Option<&[isize]>,
+    strategy: Strategy,
+) -> Result<OptimalAlignment, String> {
+    let (min, max) = crabs
+        .iter()
+        .map(|crab| crab.position)
+        .minmax()
+        .into_option()
+        .ok_or("There aren't any crabs")?;
+    // the allowed positions are searched by index, so that they don't have to be
+    // next to each other
+    let (low, high) = match allowed {
+        Some([]) => return Err("There's nowhere the crabs are allowed to meet".to_string()),
+        Some(allowed) => (0, allowed.len() as isize - 1),
+        None => (min, max),
+    };
+    let position = |index: isize| match allowed {
+        Some(allowed) => allowed[index as usize],
+        None => index,
+    };
+    let checked_total =
+        |index: isize| weighted_total_cost(crabs, cost, position(index)).ok_or_else(too_much_fuel);
+
+    let (fuel_required, cheapest) = match strategy {
+        Strategy::Exhaustive => {
+            let mut fuel_required = usize::MAX;
+            let mut cheapest = vec![];
+            for index in low..=high {
+                let fuel = checked_total(index)?;
+                if fuel < fuel_required {
+                    fuel_required = fuel;
+                    cheapest.clear();
+                }
+                if fuel == fuel_required {
+                    add_to_runs(&mut cheapest, index);
+                }
+            }
+            (fuel_required, cheapest)
+        }
+        // a weighted total of convex costs is still convex, but its minimum isn't at the
+        // plain median or mean anymore
+        _ => {
+            // a convex total is at its biggest at one end or the other,
+            // so if both of those fit, everything in between does too
+            checked_total(low)?;
+            checked_total(high)?;
+            let total = |index: isize| checked_total(index).unwrap_or(usize::MAX);
+            let (best, fuel_required) = ternary_search(low, high, total)
+                .map(|index| (index, total(index)))
+                .min_by_key(|&(index, fuel)| (fuel, index))
+                .unwrap();
+            let cheapest = cheapest_run(low, high, best, fuel_required, total);
+            (fuel_required, vec![cheapest])
+        }
+    };
+    let positions = match allowed {
+        Some(_) => cheapest
+            .into_iter()
+            .flatten()
+            .fold(vec![], |mut runs, index| {
+                add_to_runs(&mut runs, position(index));
+                runs
+            }),
+        None => cheapest,
+    };
+    Ok(OptimalAlignment {
+        fuel_required,
+        positions,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MeetingPlan {
+    pub fuel_required: usize,
+    /// Where the crabs meet, in order
+    pub meeting_points: Vec<isize>,
+    /// Which meeting point each crab goes to, in the order the crabs were given
+    pub assignments: Vec<usize>,
+}
+
+/// Splits the crabs between at most `k` meeting points, so that they burn as little fuel
+/// as possible (the k-median problem, on a line).
+///
+/// Moving further must never cost less, so that every crab goes to its nearest meeting point,
+/// which means each meeting point gathers a run of neighbouring crabs. That makes it a matter
+/// of choosing where to split the crabs, which is cubic in the number of distinct positions.
+///
+/// Fails if `k` is zero, or there aren't any crabs, or anywhere they're allowed to meet,
+/// or if moving further ever costs less
+pub fn best_meeting_points(
+    crabs: &[Crab],
+    cost: &impl CostFunction,
+    allowed: Option<&[isize]>,
+    k: usize,
+) -> Result<MeetingPlan, String> {
+    if k == 0 {
+        return Err("There has to be at least one meeting point".to_string());
+    }
+    // crabs in the same place always go to the same meeting point
+    let groups = crabs
+        .iter()
+        .into_group_map_by(|crab| crab.position)
+        .into_iter()
+        .map(|(position, crabs)| {
+            let weight = crabs
+                .iter()
+                .try_fold(0usize, |sum, crab| sum.checked_add(crab.weight))
+                .ok_or_else(too_much_fuel)?;
+            Ok(Crab::new(position, weight))
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .sorted_by_key(|crab| crab.position)
+        .collect_vec();
+    let allowed = allowed.map(|allowed| allowed.iter().copied().sorted().dedup().collect_vec());
+
+    let max_distance = max_distance(&groups, allowed.as_deref());
+    if let Some(distance) = cost.first_decrease(max_distance) {
+        return Err(format!(
+            "Moving {} steps costs less than moving {}, so crabs won't always go to the \
+             nearest meeting point",
+            distance + 1,
+            distance
+        ));
+    }
+    // whatever suits every crab and meeting point suits any run of them too
+    let strategy = cost.strategy(max_distance);
+
+    // cheapest[start][end - start - 1] is the cheapest way to gather groups[start..end]
+    let cheapest = (0..groups.len())
+        .map(|start| {
+            ((start + 1)..=groups.len())
+                .map(|end| cheapest_with(&groups[start..end], cost, allowed.as_deref(), strategy))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let gather = |start: usize, end: usize| &cheapest[start][end - start - 1];
+
+    // best[points][end] is the least fuel to gather groups[..end] at exactly `points`
+    // meeting points, along with where the last meeting point's run starts
+    let max_points = k.min(groups.len());
+    let mut best: Vec<Vec<Option<(usize, usize)>>> =
+        vec![vec![None; groups.len() + 1]; max_points + 1];
+    best[0][0] = Some((0, 0));
+    for points in 1..=max_points {
+        for end in points..=groups.len() {
+            let mut options = vec![];
+            let fewer_points = best[points - 1].iter().enumerate();
+            for (start, &previous) in fewer_points.take(end).skip(points - 1) {
+                if let Some((fuel, _)) = previous {
+                    let fuel = fuel
+                        .checked_add(gather(start, end).fuel_required)
+                        .ok_or_else(too_much_fuel)?;
+                    options.push((fuel, start));
+                }
+            }
+            best[points][end] = options.into_iter().min();
+        }
+    }
+
+    let (fuel_required, points) = (1..=max_points)
+        .filter_map(|points| Some((best[points][groups.len()]?.0, points)))
+        .min()
+        .ok_or("There aren't any crabs")?;
+    let mut runs = vec![];
+    let (mut points, mut end) = (points, groups.len());
+    while points > 0 {
+        let (_, start) = best[points][end].unwrap();
+        runs.push((start, end));
+        points -= 1;
+        end = start;
+    }
+    runs.reverse();
+
+    // runs next to each other might pick the same place to meet
+    let run_points = runs
+        .iter()
+        .map(|&(start, end)| *gather(start, end).positions[0].start())
+        .collect_vec();
+    let meeting_points = run_points.iter().copied().sorted().dedup().collect_vec();
+    let assignments = crabs
+        .iter()
+        .map(|crab| {
+            let group = groups
+                .binary_search_by_key(&crab.position, |group| group.position)
+                .unwrap();
+            let run = runs.iter().position(|&(_, end)| group < end).unwrap();
+            meeting_points.binary_search(&run_points[run]).unwrap()
+        })
+        .collect();
+
+    Ok(MeetingPlan {
+        fuel_required,
+        meeting_points,
+        assignments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::alignment::{cheapest_alignments, Custom, Linear, Quadratic, Triangular};
+    use super::*;
+
+    const EXAMPLE_INPUT: [isize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    fn unweighted(positions: &[isize]) -> Vec<Crab> {
+        positions.iter().map(|&it| Crab::new(it, 1)).collect()
+    }
+
+    fn check_against_every_target(
+        crabs: &[Crab],
+        cost: &impl CostFunction,
+        allowed: Option<&[isize]>,
+    ) {
+        let targets = match allowed {
+            Some(allowed) => allowed.iter().copied().sorted().dedup().collect_vec(),
+            None => {
+                let (min, max) = crabs
+                    .iter()
+                    .map(|crab| crab.position)
+                    .minmax()
+                    .into_option()
+                    .unwrap();
+                (min..=max).collect()
+            }
+        };
+        let fuel_required = targets
+            .iter()
+            .map(|&target| weighted_total_cost(crabs, cost, target).unwrap())
+            .min()
+            .unwrap();
+        let positions = targets
+            .iter()
+            .copied()
+            .filter(|&target| weighted_total_cost(crabs, cost, target) == Some(fuel_required))
+            .fold(vec![], |mut runs, target| {
+                add_to_runs(&mut runs, target);
+                runs
+            });
+        assert_eq!(
+            cheapest_weighted_alignment(crabs, cost, allowed),
+            Ok(OptimalAlignment {
+                fuel_required,
+                positions
+            }),
+            "{:?} allowing {:?}",
+            crabs,
+            allowed
+        );
+    }
+
+    #[test]
+    fn test_unit_weights() {
+        let crabs = unweighted(&EXAMPLE_INPUT);
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, None).ok(),
+            cheapest_alignments(&EXAMPLE_INPUT, &Linear)
+        );
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Triangular, None).ok(),
+            cheapest_alignments(&EXAMPLE_INPUT, &Triangular)
+        );
+    }
+
+    #[test]
+    fn test_weights() {
+        let crabs = [Crab::new(0, 1), Crab::new(10, 3)];
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, None),
+            Ok(OptimalAlignment {
+                fuel_required: 10,
+                positions: vec![10..=10]
+            })
+        );
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Triangular, None),
+            Ok(OptimalAlignment {
+                fuel_required: 45,
+                positions: vec![8..=8]
+            })
+        );
+
+        let crabs = [
+            Crab::new(-4, 2),
+            Crab::new(3, 0),
+            Crab::new(5, 7),
+            Crab::new(20, 1),
+            Crab::new(21, 5),
+        ];
+        check_against_every_target(&crabs, &Linear, None);
+        check_against_every_target(&crabs, &Triangular, None);
+        check_against_every_target(&crabs, &Quadratic, None);
+        check_against_every_target(&crabs, &Custom(|d| (d as f64).sqrt() as usize), None);
+    }
+
+    #[test]
+    fn test_allowed_positions() {
+        let crabs = unweighted(&EXAMPLE_INPUT);
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, Some(&[10, -3, 6])),
+            Ok(OptimalAlignment {
+                fuel_required: 49,
+                positions: vec![6..=6]
+            })
+        );
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, Some(&[])),
+            Err("There's nowhere the crabs are allowed to meet".to_string())
+        );
+        assert_eq!(
+            cheapest_weighted_alignment(&[], &Linear, None),
+            Err("There aren't any crabs".to_string())
+        );
+
+        let allowed = [-10, 3, 3, 8, 9, 30, 15];
+        check_against_every_target(&crabs, &Linear, Some(&allowed));
+        check_against_every_target(&crabs, &Triangular, Some(&allowed));
+        check_against_every_target(&crabs, &Custom(|d| d % 4), Some(&allowed));
+    }
+
+    #[test]
+    fn test_far_apart() {
+        let far = 1_000_000_000_000;
+        let crabs = [Crab::new(0, 1), Crab::new(far, 1)];
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, None),
+            Ok(OptimalAlignment {
+                fuel_required: far as usize,
+                positions: vec![0..=far]
+            })
+        );
+        // triangular costs would overflow that far out
+        let crabs = [
+            Crab::new(0, 1),
+            Crab::new(1_000_000_000, 3),
+            Crab::new(-1_000_000_000, 2),
+        ];
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Triangular, None),
+            Ok(OptimalAlignment {
+                fuel_required: 2_416_666_669_166_666_667,
+                positions: vec![166_666_667..=166_666_667]
+            })
+        );
+        let crabs = [Crab::new(0, 1), Crab::new(far, 3), Crab::new(-far, 2)];
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, Some(&[far + 7, far - 1, -5])),
+            Ok(OptimalAlignment {
+                fuel_required: 5 * far as usize,
+                positions: vec![(far - 1)..=(far - 1)]
+            })
+        );
+
+        let crabs = [
+            Crab::new(0, 1),
+            Crab::new(1, 1),
+            Crab::new(far, 1),
+            Crab::new(far + 3, 1),
+            Crab::new(-far, 1),
+        ];
+        assert_eq!(
+            best_meeting_points(&crabs, &Linear, None, 3),
+            Ok(MeetingPlan {
+                fuel_required: 4,
+                meeting_points: vec![-far, 0, far],
+                assignments: vec![1, 1, 2, 2, 0]
+            })
+        );
+    }
+
+    #[test]
+    fn test_meeting_points() {
+        let crabs = unweighted(&[0, 11, 1, 10]);
+        assert_eq!(
+            best_meeting_points(&crabs, &Linear, None, 2),
+            Ok(MeetingPlan {
+                fuel_required: 2,
+                meeting_points: vec![0, 10],
+                assignments: vec![0, 1, 0, 1]
+            })
+        );
+        assert_eq!(
+            best_meeting_points(&crabs, &Linear, Some(&[5]), 2),
+            Ok(MeetingPlan {
+                fuel_required: 20,
+                meeting_points: vec![5],
+                assignments: vec![0, 0, 0, 0]
+            })
+        );
+        assert_eq!(
+            best_meeting_points(&crabs, &Linear, None, 10),
+            Ok(MeetingPlan {
+                fuel_required: 0,
+                meeting_points: vec![0, 1, 10, 11],
+                assignments: vec![0, 3, 1, 2]
+            })
+        );
+        assert!(best_meeting_points(&crabs, &Linear, None, 0).is_err());
+        assert!(best_meeting_points(&[], &Linear, None, 2).is_err());
+    }
+
+    #[test]
+    fn test_meeting_points_need_increasing_costs() {
+        // a crab 4 steps away would rather go to a meeting point that's further
+        let crabs = unweighted(&[0, 4, 5, 9]);
+        assert_eq!(
+            best_meeting_points(&crabs, &Custom(|d| d % 4), None, 2),
+            Err(
+                "Moving 4 steps costs less than moving 3, so crabs won't always go to the \
+                 nearest meeting point"
+                    .to_string()
+            )
+        );
+        // the costs only have to keep going up as far as the crabs are apart
+        let capped = Custom(|d| if d <= 9 { d } else { 0 });
+        assert_eq!(
+            best_meeting_points(&crabs, &capped, None, 2).map(|plan| plan.fuel_required),
+            Ok(5)
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let crabs = [Crab::new(0, usize::MAX), Crab::new(10, 1)];
+        assert_eq!(weighted_total_cost(&crabs, &Linear, 1), None);
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Linear, None),
+            Err("The fuel required doesn't fit in a usize".to_string())
+        );
+        assert_eq!(
+            cheapest_weighted_alignment(&crabs, &Quadratic, None),
+            Err("The fuel required doesn't fit in a usize".to_string())
+        );
+        let crabs = [Crab::new(0, usize::MAX), Crab::new(0, 1), Crab::new(10, 1)];
+        assert!(best_meeting_points(&crabs, &Linear, None, 2).is_err());
+    }
+
+    #[test]
+    fn test_meeting_points_match_every_pair() {
+        let crabs = EXAMPLE_INPUT
+            .iter()
+            .zip([1, 2, 1, 5, 1, 1, 3, 1, 1, 2])
+            .map(|(&position, weight)| Crab::new(position, weight))
+            .collect_vec();
+        let fuel_for_pair = |cost: &dyn Fn(usize) -> usize, a: isize, b: isize| -> usize {
+            crabs
+                .iter()
+                .map(|crab| {
+                    let distance = (crab.position - a)
+                        .unsigned_abs()
+                        .min((crab.position - b).unsigned_abs());
+                    crab.weight * cost(distance)
+                })
+                .sum()
+        };
+
+        let linear = |d: usize| d;
+        let expected = (0..=16)
+            .tuple_combinations()
+            .map(|(a, b)| fuel_for_pair(&linear, a, b))
+            .min();
+        let plan = best_meeting_points(&crabs, &Linear, None, 2).unwrap();
+        assert_eq!(Some(plan.fuel_required), expected);
+        assert_eq!(
+            fuel_for_pair(&linear, plan.meeting_points[0], plan.meeting_points[1]),
+            plan.fuel_required
+        );
+
+        let allowed = [0, 3, 5, 12, 15];
+        let triangular = |d: usize| d * (d + 1) / 2;
+        let expected = allowed
+            .iter()
+            .tuple_combinations()
+            .map(|(&a, &b)| fuel_for_pair(&triangular, a, b))
+            .min();
+        let plan = best_meeting_points(&crabs, &Triangular, Some(&allowed), 2).unwrap();
+        assert_eq!(Some(plan.fuel_required), expected);
+    }
+}
@@ -1,9 +1,13 @@
 // Day 8: Seven Segment Search
-mod digit;
-mod solver;
+pub mod diagnostics;
+pub mod digit;
+pub mod layout;
+pub mod render;
+pub mod solver;
 
 use crate::prelude::*;
 use digit::DigitDisplay;
+use layout::{DisplayLayout, SegmentSet};
 use solver::{Decode, Solution};
 use std::str::FromStr;
 
@@ -11,6 +15,7 @@ lazy_static! {
     static ref PUZZLE_INPUT: Box<[Entry]> = include_lines!("day08_input.txt")
         .map(|line| line.parse().unwrap())
         .collect();
+    static ref SEVEN_SEGMENT: DisplayLayout = DisplayLayout::seven_segment();
 }
 
 pub fn part_one() -> usize {
@@ -22,7 +27,7 @@ pub fn part_two() -> Result<u32, String> {
 }
 
 #[derive(Debug, Clone, Copy)]
-struct Entry {
+pub struct Entry {
     patterns: [DigitDisplay; 10],
     output: [DigitDisplay; 4],
 }
@@ -65,21 +70,36 @@ impl FromStr for Entry {
 }
 
 impl Entry {
-    fn decode(&self) -> Result<u32, String> {
-        let solution = Solution::solve(&self.patterns)?;
+    /// The first wiring that turns every signal pattern into a digit
+    pub fn solve(&self) -> Result<Solution, String> {
+        Solution::solve(&SEVEN_SEGMENT, &segment_sets(self.patterns.iter()))
+    }
+
+    pub fn decode(&self) -> Result<u32, String> {
+        self.decode_with(&self.solve()?)
+    }
+
+    pub fn decode_with(&self, solution: &Solution) -> Result<u32, String> {
         self.output
             .iter()
             .rev()
             .enumerate()
             .map(|(place, digit)| -> Result<u32, String> {
                 let place_mult = 10_u32.pow(place as u32);
-                let digit = digit.decode_digit(&solution)?;
+                let digit = digit.decode_digit(solution)?;
                 Ok(digit as u32 * place_mult)
             })
             .try_fold(0, |prev, next| next.map(|next| prev + next))
     }
 }
 
+fn segment_sets<'a>(displays: impl IntoIterator<Item = &'a DigitDisplay>) -> Vec<SegmentSet> {
+    displays
+        .into_iter()
+        .map(|display| display.segment_set())
+        .collect()
+}
+
 fn count_simple_digits_in_output<'a, T>(example_input: T) -> usize
 where
     T: IntoIterator<Item = &'a Entry>,
@@ -0,0 +1,162 @@
+use super::digit::DigitDisplay;
+use super::solver::{DeductionRule, Solution, SolveReport};
+use super::{segment_sets, Entry, SEVEN_SEGMENT};
+use crate::prelude::*;
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Outcome {
+    Decoded(u32),
+    /// More than one wiring fits, and they don't all decode the output the same way
+    Ambiguous(Vec<u32>),
+    /// The smallest group of signal patterns that can't all be digits under the same wiring
+    Conflict(Vec<DigitDisplay>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EntryDiagnostics {
+    pub report: SolveReport,
+    pub outcome: Outcome,
+}
+
+impl Entry {
+    pub fn diagnose(&self) -> EntryDiagnostics {
+        let all_displays = self
+            .patterns
+            .iter()
+            .chain(self.output.iter())
+            .copied()
+            .collect_vec();
+        let report =
+            Solution::solve_with_report(&SEVEN_SEGMENT, &segment_sets(all_displays.iter()));
+        let outcome = match &report.solutions {
+            Ok(solutions) => {
+                // every solution turns the output into digits, so none of these fail
+                let values = solutions
+                    .iter()
+                    .filter_map(|solution| self.decode_with(solution).ok())
+                    .unique()
+                    .sorted()
+                    .collect_vec();
+                if let [value] = values[..] {
+                    Outcome::Decoded(value)
+                } else {
+                    Outcome::Ambiguous(values)
+                }
+            }
+            Err(_) => Outcome::Conflict(find_conflicting_displays(all_displays)),
+        };
+        EntryDiagnostics { report, outcome }
+    }
+}
+
+/// Drops displays one at a time for as long as the rest still can't be solved,
+/// so that every display left over is part of the conflict
+fn find_conflicting_displays(mut displays: Vec<DigitDisplay>) -> Vec<DigitDisplay> {
+    let mut i = 0;
+    while i < displays.len() {
+        let mut without = displays.clone();
+        without.remove(i);
+        if Solution::solve(&SEVEN_SEGMENT, &segment_sets(without.iter())).is_err() {
+            displays = without;
+        } else {
+            i += 1;
+        }
+    }
+    displays
+}
+
+impl Display for EntryDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for pin in self.report.pins.iter() {
+            let rule = match pin.rule {
+                DeductionRule::SegmentCount(pattern) => {
+                    format!("segment count {}", SEVEN_SEGMENT.format_pattern(pattern))
+                }
+                DeductionRule::Elimination => "elimination".to_string(),
+                DeductionRule::BruteForce => "brute force".to_string(),
+            };
+            writeln!(
+                f,
+                "{} -> {}: {}",
+                SEVEN_SEGMENT.segment_name(pin.wire),
+                SEVEN_SEGMENT.segment_name(pin.segment),
+                rule
+            )?;
+        }
+        writeln!(f, "tried {} connections", self.report.connections_tried)?;
+        match &self.outcome {
+            Outcome::Decoded(value) => write!(f, "decoded {}", value),
+            Outcome::Ambiguous(values) => {
+                write!(f, "could have been any of {}", values.iter().join(" "))
+            }
+            Outcome::Conflict(displays) => {
+                write!(f, "conflict between {}", displays.iter().join(" "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let entry: Entry =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"
+                .parse()
+                .unwrap();
+        let diagnostics = entry.diagnose();
+        assert_eq!(diagnostics.outcome, Outcome::Decoded(5353));
+        assert_eq!(
+            diagnostics.to_string(),
+            [
+                "g -> e: segment count abcdef",
+                "a -> c: segment count bcdefg",
+                "d -> a: segment count abef",
+                "e -> b: segment count abef",
+                "f -> d: segment count abcdeg",
+                "b -> f: elimination",
+                "c -> g: elimination",
+                "tried 7 connections",
+                "decoded 5353",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_diagnose_ambiguous() {
+        // the patterns only ever show a 1, but the output has to be decoded as a whole
+        let entry: Entry = "ab ab ab ab ab ab ab ab ab ab | ab abc ab abc"
+            .parse()
+            .unwrap();
+        assert_eq!(entry.diagnose().outcome, Outcome::Decoded(1717));
+
+        // six segments with both of the 1's lit could be a 0 or a 9
+        let entry: Entry = "ab ab ab ab ab ab ab ab ab ab | ab ab ab abcdef"
+            .parse()
+            .unwrap();
+        let diagnostics = entry.diagnose();
+        assert_eq!(diagnostics.outcome, Outcome::Ambiguous(vec![1110, 1119]));
+        assert!(diagnostics
+            .to_string()
+            .ends_with("could have been any of 1110 1119"));
+    }
+
+    #[test]
+    fn test_diagnose_conflict() {
+        // the 1 has been captured as "ac", which doesn't fit inside the 4 ("eafb")
+        let entry: Entry =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ac | cdfeb fcadb cdfeb cdbaf"
+                .parse()
+                .unwrap();
+        let diagnostics = entry.diagnose();
+        assert!(diagnostics.report.solutions.is_err());
+        assert_eq!(
+            diagnostics.outcome,
+            Outcome::Conflict(vec!["eafb".parse().unwrap(), "ac".parse().unwrap()])
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use std::{fmt::Debug, str::FromStr};
+use super::layout::SegmentSet;
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct DigitDisplay {
@@ -78,6 +82,17 @@ impl DigitDisplay {
         DigitDisplay { bitwise }
     }
 
+    pub fn from_segment_set(segments: SegmentSet) -> Self {
+        DigitDisplay {
+            bitwise: segments as u8,
+        }
+    }
+
+    /// The same segments, in the seven-segment `DisplayLayout`
+    pub fn segment_set(&self) -> SegmentSet {
+        self.bitwise as SegmentSet
+    }
+
     pub fn from_single_segment(segment: Segment) -> Self {
         DigitDisplay {
             bitwise: segment.as_bit(),
@@ -146,6 +161,13 @@ impl Debug for DigitDisplay {
     }
 }
 
+impl Display for DigitDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments: String = self.segments_on().map(|it| it.as_char()).collect();
+        f.write_str(segments.as_str())
+    }
+}
+
 impl Segment {
     fn from_char(c: char) -> Result<Segment, String> {
         match c {
@@ -160,6 +182,10 @@ impl Segment {
         }
     }
 
+    pub fn as_char(&self) -> char {
+        (b'a' + self.as_index() as u8) as char
+    }
+
     pub fn as_index(&self) -> usize {
         *self as usize
     }
@@ -203,4 +229,10 @@ mod tests {
         assert_eq!(DigitDisplay::from_str("abcdefg"), Ok(EIGHT));
         assert_eq!(DigitDisplay::from_str("abcdfg"), Ok(NINE));
     }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ZERO.to_string(), "abcefg");
+        assert_eq!(DigitDisplay::from_str("gfc").unwrap().to_string(), "cfg");
+    }
 }
@@ -0,0 +1,177 @@
+use super::digit::DIGITS;
+use super::solver::Solution;
+use crate::prelude::*;
+
+/// One bit per segment, in the order of the layout's alphabet
+pub type SegmentSet = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    pub label: String,
+    pub segments: SegmentSet,
+}
+
+/// Any display made of named segments, along with every glyph it's able to show.
+/// The puzzle's seven-segment digits are just one of these.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayLayout {
+    alphabet: Vec<char>,
+    glyphs: Vec<Glyph>,
+}
+
+impl DisplayLayout {
+    pub fn new(alphabet: &str, glyphs: &[(&str, &str)]) -> Result<Self, String> {
+        let alphabet: Vec<char> = alphabet.chars().collect();
+        if alphabet.is_empty() {
+            return Err("Layouts need at least one segment".to_string());
+        }
+        if alphabet.len() > SegmentSet::BITS as usize {
+            return Err(format!(
+                "Layouts can have at most {} segments, but got {}",
+                SegmentSet::BITS,
+                alphabet.len()
+            ));
+        }
+        if let Some(duplicate) = alphabet.iter().duplicates().next() {
+            return Err(format!(
+                "Segment '{}' appears twice in the alphabet",
+                duplicate
+            ));
+        }
+
+        let mut layout = DisplayLayout {
+            alphabet,
+            glyphs: Vec::with_capacity(glyphs.len()),
+        };
+        for (label, segments) in glyphs {
+            let segments = layout.parse_pattern(segments)?;
+            if let Some(existing) = layout.glyph(segments) {
+                return Err(format!(
+                    "Glyphs '{}' and '{}' light up the same segments",
+                    existing.label, label
+                ));
+            }
+            layout.glyphs.push(Glyph {
+                label: label.to_string(),
+                segments,
+            });
+        }
+        Ok(layout)
+    }
+
+    /// The digits from the puzzle, with segments named `a` through `g`
+    pub fn seven_segment() -> Self {
+        let glyphs = DIGITS
+            .iter()
+            .enumerate()
+            .map(|(digit, display)| Glyph {
+                label: digit.to_string(),
+                segments: display
+                    .segments_on()
+                    .map(|segment| 1 << segment.as_index())
+                    .fold(0, |a, b| a | b),
+            })
+            .collect();
+        DisplayLayout {
+            alphabet: "abcdefg".chars().collect(),
+            glyphs,
+        }
+    }
+
+    pub fn parse_pattern(&self, s: &str) -> Result<SegmentSet, String> {
+        s.chars().try_fold(0, |set, c| {
+            let index = self
+                .alphabet
+                .iter()
+                .position(|&it| it == c)
+                .ok_or(format!("'{}' isn't a segment in this layout", c))?;
+            Ok(set | 1 << index)
+        })
+    }
+
+    pub fn format_pattern(&self, pattern: SegmentSet) -> String {
+        self.alphabet
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| pattern & 1 << i != 0)
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    pub fn glyph(&self, segments: SegmentSet) -> Option<&Glyph> {
+        self.glyphs.iter().find(|glyph| glyph.segments == segments)
+    }
+
+    pub fn decode(&self, solution: &Solution, scrambled: SegmentSet) -> Result<&Glyph, String> {
+        let decoded = solution.decode(scrambled);
+        self.glyph(decoded).ok_or(format!(
+            "Couldn't find a matching glyph for decoded pattern '{}'",
+            self.format_pattern(decoded)
+        ))
+    }
+
+    pub fn glyphs(&self) -> &[Glyph] {
+        &self.glyphs
+    }
+
+    /// How many segments the display has
+    pub fn segment_count(&self) -> usize {
+        self.alphabet.len()
+    }
+
+    pub fn segment_name(&self, segment: usize) -> char {
+        self.alphabet[segment]
+    }
+
+    pub fn all_segments(&self) -> SegmentSet {
+        SegmentSet::MAX >> (SegmentSet::BITS as usize - self.alphabet.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seven_segment() {
+        let layout = DisplayLayout::seven_segment();
+        assert_eq!(layout.segment_count(), 7);
+        assert_eq!(layout.all_segments(), 0b1111111);
+        let eight = layout.parse_pattern("gfedcba").unwrap();
+        assert_eq!(layout.glyph(eight).unwrap().label, "8");
+        assert_eq!(layout.format_pattern(eight), "abcdefg");
+        assert_eq!(
+            layout.parse_pattern("ah"),
+            Err("'h' isn't a segment in this layout".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        assert_eq!(
+            DisplayLayout::new("", &[]),
+            Err("Layouts need at least one segment".to_string())
+        );
+        let too_many: String = (0..33)
+            .map(|i| char::from_u32(0x100 + i).unwrap())
+            .collect();
+        assert_eq!(
+            DisplayLayout::new(&too_many, &[]),
+            Err("Layouts can have at most 32 segments, but got 33".to_string())
+        );
+        assert_eq!(
+            DisplayLayout::new("xyx", &[]),
+            Err("Segment 'x' appears twice in the alphabet".to_string())
+        );
+        assert_eq!(
+            DisplayLayout::new("xyz", &[("1", "x"), ("one", "x")]),
+            Err("Glyphs '1' and 'one' light up the same segments".to_string())
+        );
+
+        let widest: String = (0..32)
+            .map(|i| char::from_u32(0x100 + i).unwrap())
+            .collect();
+        let layout = DisplayLayout::new(&widest, &[]).unwrap();
+        assert_eq!(layout.all_segments(), SegmentSet::MAX);
+    }
+}
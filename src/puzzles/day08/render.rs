@@ -0,0 +1,172 @@
+use super::digit::{DigitDisplay, Segment};
+use super::solver::Decode;
+use super::Entry;
+use crate::prelude::*;
+
+const GAP: &str = "  ";
+
+/// Draws a display like the puzzle does, with lit segments spelled out with their own letter
+/// and unlit ones as dots
+fn draw(display: &DigitDisplay) -> [String; 7] {
+    let lit: Vec<Segment> = display.segments_on().collect();
+    let segment = |segment: Segment| {
+        if lit.contains(&segment) {
+            segment.as_char()
+        } else {
+            '.'
+        }
+    };
+    let horizontal = |s: Segment| format!(" {} ", segment(s).to_string().repeat(4));
+    let vertical =
+        |left: Segment, right: Segment| format!("{}    {}", segment(left), segment(right));
+    [
+        horizontal(Segment::A),
+        vertical(Segment::B, Segment::C),
+        vertical(Segment::B, Segment::C),
+        horizontal(Segment::D),
+        vertical(Segment::E, Segment::F),
+        vertical(Segment::E, Segment::F),
+        horizontal(Segment::G),
+    ]
+}
+
+/// Lines of labeled displays next to each other, with each label centered above its display
+fn render_labeled_lines(displays: &[(String, DigitDisplay)]) -> Vec<String> {
+    let columns = displays
+        .iter()
+        .map(|(label, display)| {
+            let width = label.len().max(6);
+            std::iter::once(format!("{:^width$}", label, width = width))
+                .chain(
+                    draw(display)
+                        .into_iter()
+                        .map(|row| format!("{:^width$}", row, width = width)),
+                )
+                .collect_vec()
+        })
+        .collect_vec();
+    (0..8)
+        .map(|line| {
+            columns
+                .iter()
+                .map(|column| column[line].as_str())
+                .join(GAP)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+pub fn render_displays<'a>(displays: impl IntoIterator<Item = &'a DigitDisplay>) -> String {
+    let unlabeled = displays
+        .into_iter()
+        .map(|display| (String::new(), *display))
+        .collect_vec();
+    render_labeled_lines(&unlabeled)[1..].join("\n")
+}
+
+pub fn render_labeled(displays: &[(String, DigitDisplay)]) -> String {
+    render_labeled_lines(displays).join("\n")
+}
+
+impl Entry {
+    /// The output as it was captured next to what it decodes to
+    pub fn render_decoding(&self) -> Result<String, String> {
+        let solution = self.solve()?;
+        let scrambled = self
+            .output
+            .iter()
+            .map(|display| (display.to_string(), *display))
+            .collect_vec();
+        let decoded = self
+            .output
+            .iter()
+            .map(|display| -> Result<_, String> {
+                let digit = display.decode_digit(&solution)?;
+                Ok((format!("{}:", digit), display.decode(&solution)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let scrambled_lines = render_labeled_lines(&scrambled);
+        let width = scrambled_lines.iter().map(|it| it.len()).max().unwrap_or(0);
+        let lines = scrambled_lines
+            .iter()
+            .zip(render_labeled_lines(&decoded))
+            .enumerate()
+            .map(|(i, (scrambled, decoded))| {
+                let separator = if i == 4 { " => " } else { "    " };
+                format!(
+                    "{:width$}{}{}",
+                    scrambled,
+                    separator,
+                    decoded,
+                    width = width
+                )
+            })
+            .join("\n");
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::digit::DIGITS;
+    use super::*;
+
+    #[test]
+    fn test_render_labeled() {
+        let displays = DIGITS[0..5]
+            .iter()
+            .enumerate()
+            .map(|(i, display)| (format!("{}:", i), *display))
+            .collect_vec();
+        let expected = [
+            "  0:      1:      2:      3:      4:",
+            " aaaa    ....    aaaa    aaaa    ....",
+            "b    c  .    c  .    c  .    c  b    c",
+            "b    c  .    c  .    c  .    c  b    c",
+            " ....    ....    dddd    dddd    dddd",
+            "e    f  .    f  e    .  .    f  .    f",
+            "e    f  .    f  e    .  .    f  .    f",
+            " gggg    ....    gggg    gggg    ....",
+        ]
+        .join("\n");
+        assert_eq!(render_labeled(&displays), expected);
+    }
+
+    #[test]
+    fn test_render_displays() {
+        let expected = [
+            " aaaa    ....",
+            "b    .  .    c",
+            "b    .  .    c",
+            " dddd    ....",
+            ".    f  .    f",
+            ".    f  .    f",
+            " gggg    ....",
+        ]
+        .join("\n");
+        assert_eq!(render_displays(&[DIGITS[5], DIGITS[1]]), expected);
+    }
+
+    #[test]
+    fn test_render_decoding() {
+        let entry: Entry =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"
+                .parse()
+                .unwrap();
+        // the scrambled view lights up the wires as if they were connected to the right segments
+        let expected = [
+            "bcdef   abcdf   bcdef   abcdf       5:      3:      5:      3:",
+            " ....    aaaa    ....    aaaa      aaaa    aaaa    aaaa    aaaa",
+            "b    c  b    c  b    c  b    c    b    .  .    c  b    .  .    c",
+            "b    c  b    c  b    c  b    c    b    .  .    c  b    .  .    c",
+            " dddd    dddd    dddd    dddd  =>  dddd    dddd    dddd    dddd",
+            "e    f  .    f  e    f  .    f    .    f  .    f  .    f  .    f",
+            "e    f  .    f  e    f  .    f    .    f  .    f  .    f  .    f",
+            " ....    ....    ....    ....      gggg    gggg    gggg    gggg",
+        ]
+        .join("\n");
+        assert_eq!(entry.render_decoding(), Ok(expected));
+    }
+}
@@ -1,8 +1,42 @@
-use super::digit::{self, DigitDisplay, Segment, SegmentState, ALL_SEGMENTS, DIGITS};
+use super::digit::{DigitDisplay, DIGITS};
+use super::layout::{DisplayLayout, SegmentSet};
+use crate::prelude::*;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Which real segment each scrambled wire is connected to
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Solution {
-    mapping: [Segment; ALL_SEGMENTS.len()],
+    mapping: Vec<usize>,
+}
+
+/// Why a scrambled wire ended up connected to a particular segment
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeductionRule {
+    /// The wire was in (or missing from) this pattern, and only some of the glyphs light up
+    /// that many segments
+    SegmentCount(SegmentSet),
+    /// Every other segment the wire could have been connected to was taken by another wire
+    Elimination,
+    /// Deduction ran out, so this came from the first wiring that turned every pattern into a glyph
+    BruteForce,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Pin {
+    pub wire: usize,
+    pub segment: usize,
+    pub rule: DeductionRule,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SolveReport {
+    /// In the order the wires were pinned down
+    pub pins: Vec<Pin>,
+    /// How many times the search connected a wire to a segment before it found the first
+    /// wiring that fits
+    pub connections_tried: usize,
+    /// Every wiring that turns all of the patterns into glyphs.
+    /// More than one means the patterns don't pin the wiring down.
+    pub solutions: Result<Vec<Solution>, String>,
 }
 
 pub trait Decode {
@@ -12,10 +46,7 @@ pub trait Decode {
 
 impl Decode for DigitDisplay {
     fn decode(&self, solution: &Solution) -> Self {
-        DigitDisplay::from_segments(
-            self.segments_on()
-                .map(|from_segment| solution.mapping[from_segment.as_index()]),
-        )
+        DigitDisplay::from_segment_set(solution.decode(self.segment_set()))
     }
 
     fn decode_digit(&self, solution: &Solution) -> Result<u8, String> {
@@ -39,171 +70,272 @@ impl Decode for DigitDisplay {
 }
 
 impl Solution {
-    pub fn solve(scrambled_examples: &[DigitDisplay]) -> Result<Self, String> {
-        let mut partial_solution = PartialSolution {
-            possibilities: [digit::ALL_ON; ALL_SEGMENTS.len()],
+    /// The first wiring that turns all of the scrambled patterns into glyphs
+    pub fn solve(
+        layout: &DisplayLayout,
+        scrambled_patterns: &[SegmentSet],
+    ) -> Result<Self, String> {
+        let deduced = PartialSolution::deduce(layout, scrambled_patterns, &mut vec![])?;
+        let mut found = None;
+        deduced.search(layout, scrambled_patterns, &mut |solution, _| {
+            found = Some(solution);
+            false
+        });
+        found.ok_or_else(|| NO_WIRING.to_string())
+    }
+
+    pub fn solve_all(
+        layout: &DisplayLayout,
+        scrambled_patterns: &[SegmentSet],
+    ) -> Result<Vec<Self>, String> {
+        Solution::solve_with_report(layout, scrambled_patterns).solutions
+    }
+
+    pub fn solve_with_report(
+        layout: &DisplayLayout,
+        scrambled_patterns: &[SegmentSet],
+    ) -> SolveReport {
+        let mut pins = vec![];
+        let deduced = match PartialSolution::deduce(layout, scrambled_patterns, &mut pins) {
+            Ok(deduced) => deduced,
+            Err(err) => {
+                return SolveReport {
+                    pins,
+                    connections_tried: 0,
+                    solutions: Err(err),
+                }
+            }
         };
 
-        // First: use any simple digits to narrow down the search
-        let simple_digit_examples = scrambled_examples.iter().filter(|&example| {
-            example.is_simple_digit() &&
-            // sorry 8, you're not helpful
-             *example != digit::ALL_ON
+        let mut solutions: Vec<Solution> = vec![];
+        let mut connections_tried = 0;
+        let all_tried = deduced.search(layout, scrambled_patterns, &mut |solution, tried| {
+            if solutions.is_empty() {
+                connections_tried = tried;
+            }
+            solutions.push(solution);
+            true
         });
-        for example in simple_digit_examples {
-            let matching_digit = DIGITS
-                .iter()
-                .find(|&it| it.count_segments_on() == example.count_segments_on())
-                .unwrap();
-            let matching_digit_inverted = matching_digit.inverted();
-            // each of the segments in the scrambled example must be a segment in the matching digit...
-            // and each of the segments _off_ must be an off segment!
-            for SegmentState(segment, on) in example.segment_states() {
-                let possibilities_for_segment =
-                    &mut partial_solution.possibilities[segment.as_index()];
-                *possibilities_for_segment = possibilities_for_segment.intersect(if on {
-                    matching_digit
-                } else {
-                    &matching_digit_inverted
+
+        if solutions.is_empty() {
+            return SolveReport {
+                pins,
+                connections_tried: all_tried,
+                solutions: Err(NO_WIRING.to_string()),
+            };
+        }
+        for wire in 0..layout.segment_count() {
+            if deduced.possibilities[wire].count_ones() != 1 {
+                pins.push(Pin {
+                    wire,
+                    segment: solutions[0].mapping[wire],
+                    rule: DeductionRule::BruteForce,
                 });
             }
         }
+        SolveReport {
+            pins,
+            connections_tried,
+            solutions: Ok(solutions),
+        }
+    }
 
-        // Finally, brute force possible solutions
-        let possible_solutions = partial_solution.brute_force();
+    pub fn decode(&self, scrambled: SegmentSet) -> SegmentSet {
+        self.mapping
+            .iter()
+            .enumerate()
+            .filter(|(wire, _)| scrambled & 1 << wire != 0)
+            .fold(0, |decoded, (_, segment)| decoded | 1 << segment)
+    }
 
-        // Find first possible solution that can turn all examples into recognizable digits
-        possible_solutions
-            .into_iter()
-            .find(|solution| {
-                scrambled_examples
-                    .iter()
-                    .all(|example| example.decode_digit(solution).is_ok())
-            })
-            .ok_or("Couldn't find a solution".to_string())
+    pub fn connected_segment(&self, wire: usize) -> usize {
+        self.mapping[wire]
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+const NO_WIRING: &str = "No wiring turns every pattern into a glyph";
+
+/// The segments each wire could still be connected to
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct PartialSolution {
-    possibilities: [DigitDisplay; ALL_SEGMENTS.len()],
+    possibilities: Vec<SegmentSet>,
 }
 
 impl PartialSolution {
+    /// Narrows down every wire as far as the patterns' sizes allow, without guessing
+    fn deduce(
+        layout: &DisplayLayout,
+        scrambled_patterns: &[SegmentSet],
+        pins: &mut Vec<Pin>,
+    ) -> Result<PartialSolution, String> {
+        let all_segments = layout.all_segments();
+        let mut partial_solution = PartialSolution {
+            possibilities: vec![all_segments; layout.segment_count()],
+        };
+
+        // A lit wire has to be connected to a segment in one of the glyphs the pattern could be,
+        // and an unlit wire can't be connected to a segment that all of those glyphs have in common.
+        for &pattern in scrambled_patterns {
+            let before = partial_solution.clone();
+            let size = pattern.count_ones();
+            let candidates = layout
+                .glyphs()
+                .iter()
+                .filter(|glyph| glyph.segments.count_ones() == size)
+                .map(|glyph| glyph.segments)
+                .collect_vec();
+            if candidates.is_empty() {
+                return Err(format!(
+                    "No glyph lights up {} segments like '{}' does",
+                    size,
+                    layout.format_pattern(pattern)
+                ));
+            }
+            let union = candidates.iter().fold(0, |a, b| a | b);
+            let intersection = candidates.iter().fold(all_segments, |a, b| a & b);
+            for (wire, possibilities) in partial_solution.possibilities.iter_mut().enumerate() {
+                if pattern & 1 << wire != 0 {
+                    *possibilities &= union;
+                } else {
+                    *possibilities &= !intersection;
+                }
+            }
+            partial_solution.record_pins(&before, DeductionRule::SegmentCount(pattern), pins);
+        }
+
+        let deduced = partial_solution.deduce_until_stable();
+        deduced.record_pins(&partial_solution, DeductionRule::Elimination, pins);
+        if let Some(wire) = deduced.possibilities.iter().position(|&it| it == 0) {
+            return Err(format!(
+                "Wire '{}' can't be connected to any segment",
+                layout.segment_name(wire)
+            ));
+        }
+        Ok(deduced)
+    }
+
+    /// A segment that's the only option for one wire isn't an option for any other wire
     fn deduce_until_stable(&self) -> PartialSolution {
-        let mut current = *self;
-        let mut prev = current;
+        let mut current = self.clone();
         loop {
-            let solved: Box<[_]> = current
+            let next = current
                 .possibilities
                 .iter()
                 .enumerate()
-                .filter_map(|(i, possibilities)| {
-                    let segments: Box<[Segment]> = possibilities.segments_on().collect();
-                    if segments.len() == 1 {
-                        Some((i, segments[0]))
-                    } else {
-                        None
-                    }
+                .map(|(wire, &possibilities)| {
+                    let taken = current
+                        .possibilities
+                        .iter()
+                        .enumerate()
+                        .filter(|&(other, it)| other != wire && it.count_ones() == 1)
+                        .fold(0, |a, (_, b)| a | b);
+                    possibilities & !taken
                 })
-                .collect();
-
-            for (solution_segment_index, segment_possibilities) in
-                current.possibilities.iter_mut().enumerate()
-            {
-                // remove possible segments that are solved for other solution segments
-                *segment_possibilities =
-                    DigitDisplay::from_segments(segment_possibilities.segments_on().filter(
-                        |possible_segment| {
-                            !solved
-                                .iter()
-                                .filter(|(solved_segment_index, _)| {
-                                    *solved_segment_index != solution_segment_index
-                                })
-                                .any(|(_, solved_segment)| possible_segment == solved_segment)
-                        },
-                    ))
-            }
-
-            if current == prev {
+                .collect_vec();
+            if next == current.possibilities {
                 break;
             }
-            prev = current;
+            current.possibilities = next;
         }
         current
     }
 
-    // profile: is returning a vec faster than a boxed iterator?
-    // also, why couldn't I use a boxed slice? (Box<[Solution]>)
-    fn brute_force(&self) -> Vec<Solution> {
-        let deduced = self.deduce_until_stable();
-        // TODO: check if any segment has no possibilities
-        if let Ok(solution) = deduced.into_solution() {
-            return vec![solution];
+    /// Adds a pin for every wire that's been narrowed down to one segment since `before`
+    fn record_pins(&self, before: &PartialSolution, rule: DeductionRule, pins: &mut Vec<Pin>) {
+        for (wire, possibilities) in self.possibilities.iter().enumerate() {
+            if possibilities.count_ones() == 1 && before.possibilities[wire].count_ones() != 1 {
+                pins.push(Pin {
+                    wire,
+                    segment: possibilities.trailing_zeros() as usize,
+                    rule,
+                });
+            }
         }
+    }
 
-        let first_unsolved = deduced
-            .possibilities
-            .iter()
-            .enumerate()
-            .find(|(_, possibilities_for_segment)| {
-                possibilities_for_segment.count_segments_on() > 1
-            })
-            .unwrap();
-
-        first_unsolved
-            .1
-            .segments_on()
-            .flat_map(|segment| {
-                let mut option = deduced;
-                option.possibilities[first_unsolved.0] = DigitDisplay::from_single_segment(segment);
-                option.brute_force()
-            })
-            .collect()
+    /// Connects the wires one at a time, backing out of any connection that leaves a pattern
+    /// without a glyph it could be. `found` is called with every complete wiring and how many
+    /// connections have been tried so far, and stops the search by returning false.
+    /// Returns how many connections were tried altogether.
+    fn search(
+        &self,
+        layout: &DisplayLayout,
+        scrambled_patterns: &[SegmentSet],
+        found: &mut impl FnMut(Solution, usize) -> bool,
+    ) -> usize {
+        // trying the most constrained wires first keeps the search small
+        let order = (0..layout.segment_count())
+            .sorted_by_key(|&wire| self.possibilities[wire].count_ones())
+            .collect_vec();
+        let mut search = Search {
+            layout,
+            scrambled_patterns,
+            possibilities: &self.possibilities,
+            mapping: vec![None; layout.segment_count()],
+            connections_tried: 0,
+            found,
+        };
+        search.connect(&order);
+        search.connections_tried
     }
+}
 
-    fn into_solution(&self) -> Result<Solution, String> {
-        let mapping_results: Box<[Result<Segment, _>]> = self
-            .possibilities
-            .into_iter()
-            .enumerate()
-            .map(|(i, segment_possibilities)| {
-                let segments_on: Box<[Segment]> = segment_possibilities.segments_on().collect();
-                let segment = Segment::try_from(i as u8).unwrap();
-                match &segments_on[..] {
-                    [single] => Ok(*single),
-                    _ => Err(format!(
-                        "Need exactly one possibility for {:?}: {:?}",
-                        segment, segments_on
-                    )),
-                }
-            })
-            .collect();
-        let errors: Box<[&str]> = mapping_results
-            .iter()
-            .filter_map(|result| match result {
-                Ok(_) => None,
-                Err(err) => Some(err.as_str()),
-            })
-            .collect();
+struct Search<'a, F: FnMut(Solution, usize) -> bool> {
+    layout: &'a DisplayLayout,
+    scrambled_patterns: &'a [SegmentSet],
+    possibilities: &'a [SegmentSet],
+    mapping: Vec<Option<usize>>,
+    connections_tried: usize,
+    found: &'a mut F,
+}
 
-        if errors.len() > 0 {
-            return Err(format!(
-                "Couldn't find a solution: {:?}",
-                self.possibilities
-            ));
-        }
+impl<'a, F: FnMut(Solution, usize) -> bool> Search<'a, F> {
+    /// Returns false once the search should stop
+    fn connect(&mut self, order: &[usize]) -> bool {
+        let (&wire, rest) = match order.split_first() {
+            Some(next) => next,
+            None => {
+                let solution = Solution {
+                    mapping: self.mapping.iter().map(|it| it.unwrap()).collect(),
+                };
+                return (self.found)(solution, self.connections_tried);
+            }
+        };
 
-        let mut mapping = [Segment::A; ALL_SEGMENTS.len()];
-        // since we checked for errors above, we know that we have the right size of results
-        for (i, segment) in mapping_results
-            .into_iter()
-            .map(|it| it.as_ref().unwrap())
-            .enumerate()
-        {
-            mapping[i] = *segment;
+        let used: SegmentSet = self.mapping.iter().flatten().fold(0, |a, b| a | 1 << b);
+        let mut keep_going = true;
+        for segment in 0..self.layout.segment_count() {
+            if self.possibilities[wire] & !used & 1 << segment == 0 {
+                continue;
+            }
+            self.connections_tried += 1;
+            self.mapping[wire] = Some(segment);
+            if self.is_consistent() && !self.connect(rest) {
+                keep_going = false;
+                break;
+            }
         }
-        Ok(Solution { mapping })
+        self.mapping[wire] = None;
+        keep_going
+    }
+
+    /// Whether every pattern could still become a glyph, given the wires connected so far
+    fn is_consistent(&self) -> bool {
+        self.scrambled_patterns.iter().all(|&pattern| {
+            let (lit, unlit) = self.mapping.iter().enumerate().fold(
+                (0, 0),
+                |(lit, unlit): (SegmentSet, SegmentSet), (wire, segment)| match segment {
+                    Some(segment) if pattern & 1 << wire != 0 => (lit | 1 << segment, unlit),
+                    Some(segment) => (lit, unlit | 1 << segment),
+                    None => (lit, unlit),
+                },
+            );
+            self.layout.glyphs().iter().any(|glyph| {
+                glyph.segments.count_ones() == pattern.count_ones()
+                    && glyph.segments & lit == lit
+                    && glyph.segments & unlit == 0
+            })
+        })
     }
 }
 
@@ -213,18 +345,19 @@ mod tests {
 
     use super::*;
 
+    fn parse_patterns(layout: &DisplayLayout, patterns: &str) -> Vec<SegmentSet> {
+        patterns
+            .split_whitespace()
+            .map(|it| layout.parse_pattern(it).unwrap())
+            .collect()
+    }
+
+    const EXAMPLE_PATTERNS: &str = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab";
+
     #[test]
     fn test_decode() {
         let solution = Solution {
-            mapping: [
-                Segment::C,
-                Segment::F,
-                Segment::G,
-                Segment::A,
-                Segment::B,
-                Segment::D,
-                Segment::E,
-            ],
+            mapping: vec![2, 5, 6, 0, 1, 3, 4],
         };
         let scrambled = DigitDisplay::from_str("cdfbe").unwrap();
         assert_eq!(scrambled.decode_digit(&solution), Ok(5));
@@ -232,27 +365,148 @@ mod tests {
 
     #[test]
     fn test_solve() {
-        let examples = [
-            "acedgfb", "cdfbe", "gcdfa", "fbcad", "dab", "cefabd", "cdfgeb", "eafb", "cagedb", "ab",
-        ]
-        .into_iter()
-        .map(|it| DigitDisplay::from_str(it))
-        .collect::<Result<Box<[DigitDisplay]>, _>>()
-        .unwrap();
-        let result = Solution::solve(&examples);
+        let layout = DisplayLayout::seven_segment();
+        let result = Solution::solve(&layout, &parse_patterns(&layout, EXAMPLE_PATTERNS));
         assert_eq!(
             result,
             Ok(Solution {
-                mapping: [
-                    Segment::C,
-                    Segment::F,
-                    Segment::G,
-                    Segment::A,
-                    Segment::B,
-                    Segment::D,
-                    Segment::E,
-                ],
+                mapping: vec![2, 5, 6, 0, 1, 3, 4],
+            })
+        );
+
+        let output = parse_patterns(&layout, "cdfeb fcadb cdfeb cdbaf")
+            .into_iter()
+            .map(|it| {
+                layout
+                    .decode(result.as_ref().unwrap(), it)
+                    .unwrap()
+                    .label
+                    .as_str()
             })
+            .join("");
+        assert_eq!(output, "5353");
+    }
+
+    #[test]
+    fn test_solve_with_report() {
+        let layout = DisplayLayout::seven_segment();
+        let patterns = parse_patterns(&layout, EXAMPLE_PATTERNS);
+        let report = Solution::solve_with_report(&layout, &patterns);
+        assert_eq!(
+            report.solutions,
+            Ok(vec![Solution::solve(&layout, &patterns).unwrap()])
+        );
+
+        // the sizes of the patterns are enough to pin down every wire, so there's nothing to guess
+        assert_eq!(
+            report.pins[0],
+            Pin {
+                wire: 6,
+                segment: 4,
+                rule: DeductionRule::SegmentCount(patterns[5]),
+            }
+        );
+        assert_eq!(report.pins.len(), 7);
+        assert!(report
+            .pins
+            .iter()
+            .all(|pin| pin.rule != DeductionRule::BruteForce));
+        assert_eq!(report.connections_tried, 7);
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let layout = DisplayLayout::seven_segment();
+        // a and b go to c and f either way around, and the other five wires could go anywhere
+        let report = Solution::solve_with_report(&layout, &parse_patterns(&layout, "ab"));
+        assert_eq!(report.solutions.unwrap().len(), 2 * 5 * 4 * 3 * 2);
+        assert_eq!(report.pins.len(), 7);
+        assert!(report
+            .pins
+            .iter()
+            .all(|pin| pin.rule == DeductionRule::BruteForce));
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let layout = DisplayLayout::seven_segment();
+        // both of these would have to be a 1, so the wires that are off in one are on in the other
+        assert_eq!(
+            Solution::solve(&layout, &parse_patterns(&layout, "ab cd")),
+            Err("Wire 'a' can't be connected to any segment".to_string())
+        );
+        // a 1 and a 7 that don't share both of their segments
+        let report = Solution::solve_with_report(&layout, &parse_patterns(&layout, "ab cde"));
+        assert_eq!(
+            report.solutions,
+            Err("Wire 'a' can't be connected to any segment".to_string())
+        );
+
+        let layout = DisplayLayout::new("xyz", &[("1", "x"), ("3", "xyz")]).unwrap();
+        assert_eq!(
+            Solution::solve(&layout, &parse_patterns(&layout, "xy")),
+            Err("No glyph lights up 2 segments like 'xy' does".to_string())
+        );
+
+        // three different patterns, but only two glyphs they could be
+        let layout = DisplayLayout::new("xyz", &[("left", "xy"), ("right", "yz")]).unwrap();
+        assert_eq!(
+            Solution::solve_all(&layout, &parse_patterns(&layout, "xy xz yz")),
+            Err("No wiring turns every pattern into a glyph".to_string())
         );
     }
+
+    #[test]
+    fn test_custom_layout() {
+        // a three-segment "bar graph" display, wired backwards
+        let layout = DisplayLayout::new("xyz", &[("1", "x"), ("2", "xy"), ("3", "xyz")]).unwrap();
+        let solutions = Solution::solve_all(&layout, &parse_patterns(&layout, "z zy zyx")).unwrap();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            (0..3)
+                .map(|wire| solutions[0].connected_segment(wire))
+                .collect_vec(),
+            vec![2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_sixteen_segment() {
+        // a handful of letters on a display with sixteen segments, named A through P
+        let layout = DisplayLayout::new(
+            "ABCDEFGHIJKLMNOP",
+            &[
+                ("A", "ABCDGHIJ"),
+                ("E", "ABEFGHKN"),
+                ("H", "CDGHIJ"),
+                ("I", "ABEFLO"),
+                ("L", "EFGH"),
+                ("T", "ABLO"),
+                ("X", "KMNP"),
+                ("Z", "ABEFMN"),
+            ],
+        )
+        .unwrap();
+        // every wire shifted along by one
+        let scramble = |s: &str| {
+            s.chars()
+                .map(|c| (((c as u8 - b'A' + 1) % 16) + b'A') as char)
+                .collect::<String>()
+        };
+        let patterns = [
+            "ABCDGHIJ", "ABEFGHKN", "CDGHIJ", "ABEFLO", "EFGH", "ABLO", "KMNP", "ABEFMN",
+        ]
+        .into_iter()
+        .map(|it| layout.parse_pattern(&scramble(it)).unwrap())
+        .collect_vec();
+        let solutions = Solution::solve_all(&layout, &patterns).unwrap();
+        assert!(!solutions.is_empty());
+        for solution in solutions.iter() {
+            let labels = patterns
+                .iter()
+                .map(|&it| layout.decode(solution, it).unwrap().label.as_str())
+                .join("");
+            assert_eq!(labels, "AEHILTXZ");
+        }
+    }
 }
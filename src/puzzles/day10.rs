@@ -1,13 +1,14 @@
-use std::collections::VecDeque;
-
 // Day 10: Syntax Scoring
+pub mod grammar;
+
 use crate::prelude::*;
+use grammar::{Grammar, SyntaxError, Token};
 
 lazy_static! {
     static ref PUZZLE_INPUT: Box<[&'static str]> = include_lines!("day10_input.txt").collect();
 }
 
-pub fn part_one() -> Result<u32, String> {
+pub fn part_one() -> Result<u64, String> {
     compute_syntax_score(PUZZLE_INPUT.iter().copied())
 }
 
@@ -18,101 +19,215 @@ pub fn part_two() -> Result<u64, String> {
 #[derive(Debug, PartialEq, Eq)]
 enum ParseResult {
     Valid,
-    UnexpectedToken(char),
-    Incomplete { stack: Vec<char> },
+    UnexpectedToken(String),
+    /// The opening delimiters that haven't been closed yet, innermost last
+    Incomplete {
+        stack: Vec<String>,
+    },
 }
 
-fn expected_closing_token(opening_token: char) -> char {
-    match opening_token {
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-        _ => panic!("invalid opening token: {}", opening_token),
-    }
-}
+fn parse_line(grammar: &Grammar, line: &str) -> ParseResult {
+    let (tokens, unknown) = grammar.tokenize(line);
+    let first_unknown = unknown.iter().find_map(|error| match error {
+        SyntaxError::UnknownText { column, text } => Some((*column, *text)),
+        _ => None,
+    });
 
-fn parse_line(line: &str) -> ParseResult {
-    let mut chunk_stack = VecDeque::<char>::new();
-    for token in line.chars() {
-        let expected_closing = chunk_stack.back().map(|c| expected_closing_token(*c));
-        if expected_closing == Some(token) {
-            chunk_stack.pop_back();
-        } else {
-            match token {
-                '(' | '[' | '{' | '<' => chunk_stack.push_back(token),
-                _ => return ParseResult::UnexpectedToken(token),
+    let mut chunk_stack = Vec::<Token>::new();
+    for token in tokens {
+        if let Some((column, text)) = first_unknown {
+            if column < token.column {
+                return ParseResult::UnexpectedToken(text.to_string());
+            }
+        }
+        match chunk_stack.last() {
+            Some(open) if !token.opening && open.pair == token.pair => {
+                chunk_stack.pop();
             }
+            _ if token.opening => chunk_stack.push(token),
+            _ => return ParseResult::UnexpectedToken(grammar.token_text(token).to_string()),
         }
     }
+    if let Some((_, text)) = first_unknown {
+        return ParseResult::UnexpectedToken(text.to_string());
+    }
+
     if chunk_stack.is_empty() {
         ParseResult::Valid
     } else {
         ParseResult::Incomplete {
-            stack: chunk_stack.into(),
+            stack: chunk_stack
+                .into_iter()
+                .map(|token| grammar.token_text(token).to_string())
+                .collect(),
         }
     }
 }
 
-fn compute_syntax_score<'a, T>(lines: T) -> Result<u32, String>
-where
-    T: IntoIterator<Item = &'a str>,
-{
-    lines
-        .into_iter()
-        .map(parse_line)
-        .map(|result| match result {
-            ParseResult::UnexpectedToken(')') => Ok(3),
-            ParseResult::UnexpectedToken(']') => Ok(57),
-            ParseResult::UnexpectedToken('}') => Ok(1197),
-            ParseResult::UnexpectedToken('>') => Ok(25137),
-            ParseResult::UnexpectedToken(other_char) => {
-                Err(format!("invalid token: {}", other_char))
-            }
-            _ => Ok(0),
-        })
-        .try_fold(0, |prev, next| next.map(|next| prev + next))
+/// How corrupted and incomplete lines are scored. The default is the puzzle's rules.
+pub struct ScoringPolicy {
+    /// The delimiters lines are made of
+    grammar: Grammar,
+    /// Points for the first illegal delimiter on a corrupted line
+    syntax_error_points: Vec<(String, u64)>,
+    /// Points for each delimiter needed to complete an incomplete line
+    completion_points: Vec<(String, u64)>,
+    /// The score so far is multiplied by this before adding the points for the next delimiter
+    completion_base: u64,
+    /// Combines the completion scores of every incomplete line into one
+    aggregate_completion_scores: Box<dyn Fn(Vec<u64>) -> Option<u64>>,
 }
 
-fn compute_autocomplete_score<'a, T: IntoIterator<Item = &'a str>>(
-    lines: T,
-) -> Result<u64, String> {
-    let scores = lines
-        .into_iter()
-        .map(|line| autocomplete_score_for_result(&parse_line(line)))
-        .collect::<Result<Vec<Option<_>>, _>>()?;
-    let scores = scores
-        .into_iter()
-        .filter_map(|it| it)
-        .sorted()
-        .collect_vec();
-
-    if scores.len() == 0 {
-        return Err("No lines autocompleted".to_string());
+impl Default for ScoringPolicy {
+    fn default() -> Self {
+        ScoringPolicy::new(
+            Grammar::brackets(),
+            &[(")", 3), ("]", 57), ("}", 1197), (">", 25137)],
+            &[(")", 1), ("]", 2), ("}", 3), (">", 4)],
+            5,
+        )
     }
+}
+
+fn points_table(points: &[(&str, u64)]) -> Vec<(String, u64)> {
+    points
+        .iter()
+        .map(|(token, points)| (token.to_string(), *points))
+        .collect()
+}
+
+fn median(scores: Vec<u64>) -> Option<u64> {
+    let scores = scores.into_iter().sorted().collect_vec();
+    scores.get(scores.len() / 2).copied()
+}
 
-    Ok(scores[scores.len() / 2])
+fn points_for(table: &[(String, u64)], token: &str) -> Result<u64, String> {
+    table
+        .iter()
+        .find(|(it, _)| it == token)
+        .map(|(_, points)| *points)
+        .ok_or(format!("invalid token: {}", token))
 }
 
-fn autocomplete_score_for_result(parse_result: &ParseResult) -> Result<Option<u64>, String> {
-    if let ParseResult::Incomplete { stack } = parse_result {
-        stack
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineScore {
+    Valid,
+    Corrupted { token: String, points: u64 },
+    Incomplete { completion: String, points: u64 },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScoreReport {
+    pub lines: Vec<LineScore>,
+    pub syntax_score: u64,
+    /// None if no lines were incomplete
+    pub autocomplete_score: Option<u64>,
+}
+
+impl ScoringPolicy {
+    /// Scores lines made of `grammar`'s delimiters, with points for each closing delimiter.
+    /// Completion scores are combined by taking their median, like in the puzzle.
+    pub fn new(
+        grammar: Grammar,
+        syntax_error_points: &[(&str, u64)],
+        completion_points: &[(&str, u64)],
+        completion_base: u64,
+    ) -> Self {
+        ScoringPolicy {
+            grammar,
+            syntax_error_points: points_table(syntax_error_points),
+            completion_points: points_table(completion_points),
+            completion_base,
+            aggregate_completion_scores: Box::new(median),
+        }
+    }
+
+    /// Combines completion scores some other way than taking their median
+    pub fn with_aggregation(
+        mut self,
+        aggregate: impl Fn(Vec<u64>) -> Option<u64> + 'static,
+    ) -> Self {
+        self.aggregate_completion_scores = Box::new(aggregate);
+        self
+    }
+
+    pub fn score_line(&self, line: &str) -> Result<LineScore, String> {
+        match parse_line(&self.grammar, line) {
+            ParseResult::Valid => Ok(LineScore::Valid),
+            ParseResult::UnexpectedToken(token) => Ok(LineScore::Corrupted {
+                points: points_for(&self.syntax_error_points, &token)?,
+                token,
+            }),
+            ParseResult::Incomplete { stack } => {
+                let closing = stack
+                    .iter()
+                    .rev()
+                    .map(|open| self.grammar.closing_delimiter(open).unwrap())
+                    .collect_vec();
+                let completion = closing.join(self.grammar.separator());
+                let points = closing.iter().try_fold(0_u64, |score, token| {
+                    let points = points_for(&self.completion_points, token)?;
+                    score
+                        .checked_mul(self.completion_base)
+                        .and_then(|score| score.checked_add(points))
+                        .ok_or(format!("Completion score for {} overflowed", completion))
+                })?;
+                Ok(LineScore::Incomplete { completion, points })
+            }
+        }
+    }
+
+    pub fn score_lines<'a, T>(&self, lines: T) -> Result<ScoreReport, String>
+    where
+        T: IntoIterator<Item = &'a str>,
+    {
+        let lines = lines
+            .into_iter()
+            .map(|line| self.score_line(line))
+            .collect::<Result<Vec<_>, _>>()?;
+        let syntax_score = lines
             .iter()
-            .rev()
-            .map(|c| match c {
-                '(' => Ok(1),
-                '[' => Ok(2),
-                '{' => Ok(3),
-                '<' => Ok(4),
-                &other_char => Err(format!("invalid token: {}", other_char)),
+            .map(|line| match line {
+                LineScore::Corrupted { points, .. } => *points,
+                _ => 0,
             })
-            .try_fold(0 as u64, |prev, next| Ok(prev * 5 + next?))
-            .map(|it| Some(it))
-    } else {
-        Ok(None)
+            .sum();
+        let completion_scores = lines
+            .iter()
+            .filter_map(|line| match line {
+                LineScore::Incomplete { points, .. } => Some(*points),
+                _ => None,
+            })
+            .collect_vec();
+        let autocomplete_score = if completion_scores.is_empty() {
+            None
+        } else {
+            (self.aggregate_completion_scores)(completion_scores)
+        };
+        Ok(ScoreReport {
+            lines,
+            syntax_score,
+            autocomplete_score,
+        })
     }
 }
 
+fn compute_syntax_score<'a, T>(lines: T) -> Result<u64, String>
+where
+    T: IntoIterator<Item = &'a str>,
+{
+    Ok(ScoringPolicy::default().score_lines(lines)?.syntax_score)
+}
+
+fn compute_autocomplete_score<'a, T: IntoIterator<Item = &'a str>>(
+    lines: T,
+) -> Result<u64, String> {
+    ScoringPolicy::default()
+        .score_lines(lines)?
+        .autocomplete_score
+        .ok_or("No lines autocompleted".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,21 +248,35 @@ mod tests {
     #[test]
     fn test_corrupted_line() {
         assert_eq!(
-            parse_line("{([(<{}[<>[]}>{[]{[(<()>"),
-            ParseResult::UnexpectedToken('}')
+            parse_line(&Grammar::brackets(), "{([(<{}[<>[]}>{[]{[(<()>"),
+            ParseResult::UnexpectedToken("}".to_string())
         );
     }
 
     #[test]
     fn test_incomplete_line() {
         assert_eq!(
-            parse_line("(((({<>}<{<{<>}{[]{[]{}"),
+            parse_line(&Grammar::brackets(), "(((({<>}<{<{<>}{[]{[]{}"),
             ParseResult::Incomplete {
-                stack: "((((<{<{{".chars().collect()
+                stack: "((((<{<{{".chars().map(String::from).collect()
             }
         );
     }
 
+    #[test]
+    fn test_unknown_token() {
+        let grammar = Grammar::brackets();
+        assert_eq!(
+            parse_line(&grammar, "(a)"),
+            ParseResult::UnexpectedToken("a".to_string())
+        );
+        // a mismatched delimiter before the unknown text is reported first
+        assert_eq!(
+            parse_line(&grammar, "(]a"),
+            ParseResult::UnexpectedToken("]".to_string())
+        );
+    }
+
     #[test]
     fn test_syntax_score() {
         let result = compute_syntax_score(EXAMPLE_INPUT.iter().copied());
@@ -162,10 +291,21 @@ mod tests {
 
     #[test]
     fn test_autocomplete_score() {
-        let result = autocomplete_score_for_result(&parse_line(EXAMPLE_INPUT[0]));
-        assert_eq!(result, Ok(Some(288957)));
-        let result = autocomplete_score_for_result(&parse_line(EXAMPLE_INPUT[1]));
-        assert_eq!(result, Ok(Some(5566)));
+        let policy = ScoringPolicy::default();
+        assert_eq!(
+            policy.score_line(EXAMPLE_INPUT[0]),
+            Ok(LineScore::Incomplete {
+                completion: "}}]])})]".to_string(),
+                points: 288957
+            })
+        );
+        assert_eq!(
+            policy.score_line(EXAMPLE_INPUT[1]),
+            Ok(LineScore::Incomplete {
+                completion: ")}>]})".to_string(),
+                points: 5566
+            })
+        );
     }
 
     #[test]
@@ -174,6 +314,77 @@ mod tests {
         assert_eq!(result, Ok(288957));
     }
 
+    #[test]
+    fn test_score_report() {
+        let report = ScoringPolicy::default()
+            .score_lines(EXAMPLE_INPUT.iter().copied())
+            .unwrap();
+        assert_eq!(report.lines.len(), 10);
+        assert_eq!(
+            report.lines[2],
+            LineScore::Corrupted {
+                token: "}".to_string(),
+                points: 1197
+            }
+        );
+        assert_eq!(report.syntax_score, 26397);
+        assert_eq!(report.autocomplete_score, Some(288957));
+    }
+
+    #[test]
+    fn test_custom_scoring() {
+        let policy = ScoringPolicy::new(
+            Grammar::brackets(),
+            &[(")", 1), ("]", 1), ("}", 1), (">", 1)],
+            &[(")", 1), ("]", 2), ("}", 3), (">", 4)],
+            10,
+        )
+        .with_aggregation(|scores| scores.into_iter().max());
+        let report = policy.score_lines(EXAMPLE_INPUT.iter().copied()).unwrap();
+        // "}}]])})]" read as digits
+        assert_eq!(
+            report.lines[0],
+            LineScore::Incomplete {
+                completion: "}}]])})]".to_string(),
+                points: 33221312
+            }
+        );
+        assert_eq!(report.syntax_score, 5);
+        // "}}>}>))))" has the longest completion
+        assert_eq!(report.autocomplete_score, Some(334341111));
+    }
+
+    #[test]
+    fn test_custom_grammar() {
+        let policy = ScoringPolicy::new(
+            Grammar::new(&[("begin", "end"), ("(", ")")]).unwrap(),
+            &[("end", 10), (")", 20)],
+            &[("end", 1), (")", 2)],
+            5,
+        );
+        assert_eq!(
+            policy.score_line("begin ( begin end"),
+            Ok(LineScore::Incomplete {
+                completion: ") end".to_string(),
+                points: 2 * 5 + 1
+            })
+        );
+        assert_eq!(
+            policy.score_line("begin ( end )"),
+            Ok(LineScore::Corrupted {
+                token: "end".to_string(),
+                points: 10
+            })
+        );
+        assert_eq!(policy.score_line("(begin end)"), Ok(LineScore::Valid));
+    }
+
+    #[test]
+    fn test_completion_overflow() {
+        let line = "(".repeat(40);
+        assert!(ScoringPolicy::default().score_line(&line).is_err());
+    }
+
     #[test]
     fn part_two_answer() {
         let result = part_two();
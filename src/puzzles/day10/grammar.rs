@@ -0,0 +1,321 @@
+use crate::prelude::*;
+
+/// A set of opening and closing delimiters that have to be nested correctly.
+/// Delimiters can be more than one character long, and whitespace between them is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    pairs: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxError {
+    /// Text that isn't any of the grammar's delimiters
+    UnknownText { column: usize, text: char },
+    /// A closing delimiter that had to be removed
+    Unexpected { column: usize, token: String },
+    /// An opening delimiter that had to have its closing delimiter added
+    Unclosed {
+        column: usize,
+        token: String,
+        expected: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckReport {
+    /// In the order they appear in the line
+    pub errors: Vec<SyntaxError>,
+    /// The line with the fewest delimiters added or removed to make it valid
+    pub repaired: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Token {
+    pub(super) pair: usize,
+    pub(super) opening: bool,
+    pub(super) column: usize,
+}
+
+impl Grammar {
+    pub fn new(pairs: &[(&str, &str)]) -> Result<Self, String> {
+        let tokens = pairs.iter().flat_map(|(open, close)| [*open, *close]);
+        if let Some(invalid) = tokens
+            .clone()
+            .find(|token| token.is_empty() || token.contains(char::is_whitespace))
+        {
+            return Err(format!("Invalid delimiter: {:?}", invalid));
+        }
+        if let Some(duplicate) = tokens.duplicates().next() {
+            return Err(format!("Delimiter {:?} is used more than once", duplicate));
+        }
+        Ok(Grammar {
+            pairs: pairs
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect(),
+        })
+    }
+
+    /// The brackets from the puzzle
+    pub fn brackets() -> Self {
+        Grammar::new(&[("(", ")"), ("[", "]"), ("{", "}"), ("<", ">")]).unwrap()
+    }
+
+    /// The delimiter that closes `opening`, if it's an opening delimiter
+    pub fn closing_delimiter(&self, opening: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(open, _)| open == opening)
+            .map(|(_, close)| close.as_str())
+    }
+
+    pub(super) fn token_text(&self, token: Token) -> &str {
+        let (open, close) = &self.pairs[token.pair];
+        if token.opening {
+            open
+        } else {
+            close
+        }
+    }
+
+    /// Splits a line into delimiters, preferring the longest one that matches.
+    /// Columns count characters from 1.
+    pub(super) fn tokenize(&self, line: &str) -> (Vec<Token>, Vec<SyntaxError>) {
+        let mut tokens = vec![];
+        let mut errors = vec![];
+        let mut rest = line;
+        let mut column = 1;
+        while let Some(next) = rest.chars().next() {
+            if next.is_whitespace() {
+                rest = &rest[next.len_utf8()..];
+                column += 1;
+                continue;
+            }
+
+            let longest_match = self
+                .pairs
+                .iter()
+                .enumerate()
+                .flat_map(|(pair, (open, close))| [(pair, true, open), (pair, false, close)])
+                .filter(|(_, _, text)| rest.starts_with(text.as_str()))
+                .max_by_key(|(_, _, text)| text.len());
+            match longest_match {
+                Some((pair, opening, text)) => {
+                    tokens.push(Token {
+                        pair,
+                        opening,
+                        column,
+                    });
+                    rest = &rest[text.len()..];
+                    column += text.chars().count();
+                }
+                None => {
+                    errors.push(SyntaxError::UnknownText { column, text: next });
+                    rest = &rest[next.len_utf8()..];
+                    column += 1;
+                }
+            }
+        }
+        (tokens, errors)
+    }
+
+    /// Reports every error in the line, by working out the smallest number of delimiters
+    /// that need to be added or removed to fix it
+    pub fn check(&self, line: &str) -> CheckReport {
+        let (tokens, mut errors) = self.tokenize(line);
+        let n = tokens.len();
+
+        // edits[i][j] is the fewest edits that make tokens[i..j] valid on their own.
+        // matched[i][j] is the token that tokens[i] gets closed by, if it's kept as a pair.
+        let mut edits = vec![vec![0; n + 1]; n + 1];
+        let mut matched: Vec<Vec<Option<usize>>> = vec![vec![None; n + 1]; n + 1];
+        for len in 1..=n {
+            for i in 0..=(n - len) {
+                let j = i + len;
+                // either drop an unexpected closing delimiter or close an unclosed opening one
+                let mut best = (1 + edits[i + 1][j], None);
+                if tokens[i].opening {
+                    for k in (i + 1)..j {
+                        if !tokens[k].opening && tokens[k].pair == tokens[i].pair {
+                            let cost = edits[i + 1][k] + edits[k + 1][j];
+                            // on a tie, adding a closing delimiter at the end is more like autocomplete
+                            if cost < best.0 {
+                                best = (cost, Some(k));
+                            }
+                        }
+                    }
+                }
+                edits[i][j] = best.0;
+                matched[i][j] = best.1;
+            }
+        }
+
+        let mut repaired = vec![];
+        self.repair(&tokens, &matched, 0, n, &mut repaired, &mut errors);
+        errors.sort_by_key(|error| match error {
+            SyntaxError::UnknownText { column, .. }
+            | SyntaxError::Unexpected { column, .. }
+            | SyntaxError::Unclosed { column, .. } => *column,
+        });
+
+        CheckReport {
+            errors,
+            repaired: repaired.join(self.separator()),
+        }
+    }
+
+    /// Multi-character delimiters need spaces between them to stay readable
+    pub fn separator(&self) -> &'static str {
+        if self
+            .pairs
+            .iter()
+            .all(|(open, close)| open.chars().count() == 1 && close.chars().count() == 1)
+        {
+            ""
+        } else {
+            " "
+        }
+    }
+
+    fn repair<'a>(
+        &'a self,
+        tokens: &[Token],
+        matched: &[Vec<Option<usize>>],
+        start: usize,
+        end: usize,
+        repaired: &mut Vec<&'a str>,
+        errors: &mut Vec<SyntaxError>,
+    ) {
+        if start == end {
+            return;
+        }
+        let token = tokens[start];
+        match matched[start][end] {
+            Some(closing) => {
+                repaired.push(self.token_text(token));
+                self.repair(tokens, matched, start + 1, closing, repaired, errors);
+                repaired.push(self.token_text(tokens[closing]));
+                self.repair(tokens, matched, closing + 1, end, repaired, errors);
+            }
+            None if token.opening => {
+                let expected = &self.pairs[token.pair].1;
+                errors.push(SyntaxError::Unclosed {
+                    column: token.column,
+                    token: self.token_text(token).to_string(),
+                    expected: expected.clone(),
+                });
+                repaired.push(self.token_text(token));
+                self.repair(tokens, matched, start + 1, end, repaired, errors);
+                repaired.push(expected);
+            }
+            None => {
+                errors.push(SyntaxError::Unexpected {
+                    column: token.column,
+                    token: self.token_text(token).to_string(),
+                });
+                self.repair(tokens, matched, start + 1, end, repaired, errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_line() {
+        let report = Grammar::brackets().check("[<>({}){}[([])<>]]");
+        assert_eq!(report.errors, vec![]);
+        assert_eq!(report.repaired, "[<>({}){}[([])<>]]");
+    }
+
+    #[test]
+    fn test_incomplete_line() {
+        // the repair is the same as the puzzle's autocompletion
+        let report = Grammar::brackets().check("[({(<(())[]>[[{[]{<()<>>");
+        assert_eq!(report.repaired, "[({(<(())[]>[[{[]{<()<>>}}]])})]");
+        assert_eq!(report.errors.len(), 8);
+        assert_eq!(
+            report.errors[0],
+            SyntaxError::Unclosed {
+                column: 1,
+                token: "[".to_string(),
+                expected: "]".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_every_error_reported() {
+        let report = Grammar::brackets().check("(]<)>");
+        assert_eq!(
+            report.errors,
+            vec![
+                SyntaxError::Unclosed {
+                    column: 1,
+                    token: "(".to_string(),
+                    expected: ")".to_string()
+                },
+                SyntaxError::Unexpected {
+                    column: 2,
+                    token: "]".to_string()
+                },
+                SyntaxError::Unexpected {
+                    column: 4,
+                    token: ")".to_string()
+                },
+            ]
+        );
+        assert_eq!(report.repaired, "(<>)");
+    }
+
+    #[test]
+    fn test_corrupted_line_repairs_to_valid_line() {
+        let grammar = Grammar::brackets();
+        let report = grammar.check("{([(<{}[<>[]}>{[]{[(<()>");
+        assert!(!report.errors.is_empty());
+        assert_eq!(grammar.check(&report.repaired).errors, vec![]);
+    }
+
+    #[test]
+    fn test_multi_character_delimiters() {
+        let grammar = Grammar::new(&[("begin", "end"), ("(", ")")]).unwrap();
+        let report = grammar.check("begin ( begin end end");
+        assert_eq!(
+            report.errors,
+            vec![SyntaxError::Unclosed {
+                column: 7,
+                token: "(".to_string(),
+                expected: ")".to_string()
+            }]
+        );
+        assert_eq!(report.repaired, "begin ( begin end ) end");
+    }
+
+    #[test]
+    fn test_longest_delimiter_wins() {
+        let grammar = Grammar::new(&[("<", ">"), ("<<", ">>")]).unwrap();
+        assert_eq!(grammar.check("<<>>").errors, vec![]);
+        assert_eq!(grammar.check("<<>").repaired, "<< >>");
+    }
+
+    #[test]
+    fn test_unknown_text() {
+        let report = Grammar::brackets().check("(x)");
+        assert_eq!(
+            report.errors,
+            vec![SyntaxError::UnknownText {
+                column: 2,
+                text: 'x'
+            }]
+        );
+        assert_eq!(report.repaired, "()");
+    }
+
+    #[test]
+    fn test_invalid_grammar() {
+        assert!(Grammar::new(&[("(", ")"), ("[", ")")]).is_err());
+        assert!(Grammar::new(&[("", "end")]).is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 // Day 12: Passage Pathing
+pub mod paths;
+
 use crate::prelude::*;
 
 lazy_static! {
@@ -8,16 +10,16 @@ lazy_static! {
         ConnectionMap::from_lines(include_lines!("day12_input.txt")).unwrap();
 }
 
-pub fn part_one() -> usize {
+pub fn part_one() -> Result<usize, String> {
     PUZZLE_INPUT.count_paths()
 }
 
-pub fn part_two() -> usize {
+pub fn part_two() -> Result<usize, String> {
     PUZZLE_INPUT.count_paths_mk2()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-enum Node<'a> {
+pub enum Node<'a> {
     Start,
     End,
     SmallCave(&'a str),
@@ -36,10 +38,10 @@ impl<'a> Node<'a> {
     }
 }
 
-struct ConnectionMap<'a>(HashMap<Node<'a>, Vec<Node<'a>>>);
+pub struct ConnectionMap<'a>(HashMap<Node<'a>, Vec<Node<'a>>>);
 
 impl<'a> ConnectionMap<'a> {
-    fn from_lines<T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Self, String> {
+    pub fn from_lines<T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Self, String> {
         let pairs = iter.into_iter().map(|line| {
             let (from_str, to_str) = line
                 .split_once('-')
@@ -58,6 +60,7 @@ impl<'a> ConnectionMap<'a> {
                 Ok((from, to)) => result.insert_connection(from, to),
             }
         }
+
         Ok(result)
     }
 
@@ -83,8 +86,267 @@ impl<'a> ConnectionMap<'a> {
         self.insert_one_way_connection(to, from);
     }
 
-    fn count_paths(&self) -> usize {
-        self.count_paths_with_validation(|path, next| {
+    fn count_paths(&self) -> Result<usize, String> {
+        self.count_paths_with_rules(&VisitRules::small_caves_once())
+    }
+
+    fn count_paths_mk2(&self) -> Result<usize, String> {
+        self.count_paths_with_rules(&VisitRules::one_small_cave_twice())
+    }
+
+    pub fn count_paths_with_rules(&self, rules: &VisitRules<'a>) -> Result<usize, String> {
+        Ok(CaveIndex::new(self, rules)?.count_paths(rules.caves_allowed_extra_visit))
+    }
+}
+
+/// Which caves a path is allowed to go through, and how many times
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisitRules<'a> {
+    max_small_cave_visits: u32,
+    /// How many small caves can be visited once more than they're normally allowed to be
+    caves_allowed_extra_visit: u32,
+    /// Limits for particular caves, in place of the one for small caves.
+    /// Large caves can be visited any number of times unless they're given a limit here.
+    cave_limits: HashMap<Node<'a>, u32>,
+    forbidden_caves: HashSet<Node<'a>>,
+}
+
+impl<'a> VisitRules<'a> {
+    /// Small caves can be visited `max_small_cave_visits` times each, and up to
+    /// `caves_allowed_extra_visit` of them once more than that
+    pub fn new(max_small_cave_visits: u32, caves_allowed_extra_visit: u32) -> Self {
+        VisitRules {
+            max_small_cave_visits,
+            caves_allowed_extra_visit,
+            cave_limits: HashMap::new(),
+            forbidden_caves: HashSet::new(),
+        }
+    }
+
+    pub fn small_caves_once() -> Self {
+        VisitRules::new(1, 0)
+    }
+
+    pub fn one_small_cave_twice() -> Self {
+        VisitRules::new(1, 1)
+    }
+
+    /// Limits a cave to `limit` visits, whether it's small or large
+    pub fn with_limit(mut self, cave: Node<'a>, limit: u32) -> Self {
+        self.cave_limits.insert(cave, limit);
+        self
+    }
+
+    /// Keeps paths out of a cave altogether, even if it would otherwise get an extra visit
+    pub fn forbid(mut self, cave: Node<'a>) -> Self {
+        self.forbidden_caves.insert(cave);
+        self
+    }
+
+    /// `None` if the cave can be visited any number of times
+    fn limit_for(&self, cave: Node<'a>) -> Option<u32> {
+        if self.forbidden_caves.contains(&cave) {
+            Some(0)
+        } else if let Some(&limit) = self.cave_limits.get(&cave) {
+            Some(limit)
+        } else if let Node::SmallCave(_) = cave {
+            Some(self.max_small_cave_visits)
+        } else {
+            None
+        }
+    }
+
+    fn allows_extra_visit(&self, cave: Node<'a>) -> bool {
+        matches!(cave, Node::SmallCave(_)) && !self.forbidden_caves.contains(&cave)
+    }
+
+    fn allows_move(&self, path: &[Node<'a>], next: Node<'a>) -> bool {
+        let limit = match self.limit_for(next) {
+            Some(limit) => limit,
+            None => return true,
+        };
+        let visits = path.iter().filter(|&&node| node == next).count() as u32;
+        if visits < limit {
+            true
+        } else if visits == limit && self.allows_extra_visit(next) {
+            let extra_visits_used = path
+                .iter()
+                .counts()
+                .into_iter()
+                .filter(|&(&cave, visits)| {
+                    self.allows_extra_visit(cave)
+                        && self
+                            .limit_for(cave)
+                            .is_some_and(|limit| visits as u32 > limit)
+                })
+                .count() as u32;
+            extra_visits_used < self.caves_allowed_extra_visit
+        } else {
+            false
+        }
+    }
+}
+
+/// How many times a path has visited a cave, stored in a few bits of the path's visit counts
+#[derive(Debug, Clone, Copy)]
+struct VisitCounter {
+    shift: u32,
+    limit: u32,
+    allows_extra_visit: bool,
+}
+
+impl VisitCounter {
+    fn visits(&self, counts: u64) -> u32 {
+        let mask = (1 << self.bits()) - 1;
+        ((counts >> self.shift) & mask) as u32
+    }
+
+    fn bits(&self) -> u32 {
+        let max_visits = self.limit as u64 + self.allows_extra_visit as u64;
+        u64::BITS - max_visits.leading_zeros()
+    }
+}
+
+/// The caves, numbered so that the number of times a path has visited each cave
+/// fits in the bits of a single number
+struct CaveIndex {
+    connections: Vec<Vec<usize>>,
+    /// `None` for caves that can be visited any number of times
+    counters: Vec<Option<VisitCounter>>,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+type PathCountMemo = HashMap<(usize, u64, u32), usize>;
+
+impl CaveIndex {
+    fn new(map: &ConnectionMap, rules: &VisitRules) -> Result<Self, String> {
+        let nodes = map
+            .0
+            .iter()
+            .flat_map(|(from, to)| std::iter::once(from).chain(to.iter()))
+            .copied()
+            .unique()
+            .sorted()
+            .collect_vec();
+        let index_of = |node: &Node| nodes.binary_search(node).ok();
+
+        let connections: Vec<Vec<usize>> = nodes
+            .iter()
+            .map(|node| {
+                map.0
+                    .get(node)
+                    .map(|to| to.iter().map(|it| index_of(it).unwrap()).collect())
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let mut bits_so_far = 0;
+        let mut counters = Vec::with_capacity(nodes.len());
+        for &node in nodes.iter() {
+            let counter = rules.limit_for(node).map(|limit| VisitCounter {
+                shift: bits_so_far,
+                limit,
+                allows_extra_visit: rules.caves_allowed_extra_visit > 0
+                    && rules.allows_extra_visit(node),
+            });
+            if let Some(counter) = counter {
+                bits_so_far += counter.bits();
+            }
+            counters.push(counter);
+        }
+        if bits_so_far > u64::BITS {
+            return Err(format!(
+                "Keeping track of visits needs {} bits, but only {} are available",
+                bits_so_far,
+                u64::BITS
+            ));
+        }
+
+        // caves without a limit don't add to the visit counts, so a path could go back and forth
+        // between two of them forever. Nothing leads back to the start or out of the end,
+        // so those are the only caves that can't be part of a cycle.
+        let (start, end) = (index_of(&Node::Start), index_of(&Node::End));
+        let unlimited =
+            |cave: usize| counters[cave].is_none() && Some(cave) != start && Some(cave) != end;
+        for (cave, to) in connections
+            .iter()
+            .enumerate()
+            .filter(|&(cave, _)| unlimited(cave))
+        {
+            if let Some(&next) = to.iter().find(|&&next| unlimited(next)) {
+                return Err(format!(
+                    "Paths could go between {} and {} forever, since neither has a visit limit",
+                    nodes[cave].name(),
+                    nodes[next].name()
+                ));
+            }
+        }
+
+        Ok(CaveIndex {
+            connections,
+            counters,
+            start,
+            end,
+        })
+    }
+
+    /// Counts paths from start to end, where up to `extra_visits` caves
+    /// can be visited once more than their limit
+    fn count_paths(&self, extra_visits: u32) -> usize {
+        match self.start {
+            Some(start) => self.count_paths_from(start, 0, extra_visits, &mut HashMap::new()),
+            None => 0,
+        }
+    }
+
+    fn count_paths_from(
+        &self,
+        cave: usize,
+        visit_counts: u64,
+        extra_visits_left: u32,
+        memo: &mut PathCountMemo,
+    ) -> usize {
+        if Some(cave) == self.end {
+            return 1;
+        }
+        if let Some(&count) = memo.get(&(cave, visit_counts, extra_visits_left)) {
+            return count;
+        }
+
+        let mut count = 0;
+        for &next in self.connections[cave].iter() {
+            count += match self.counters[next] {
+                None => self.count_paths_from(next, visit_counts, extra_visits_left, memo),
+                Some(counter) => {
+                    let visits = counter.visits(visit_counts);
+                    let counted = visit_counts + (1 << counter.shift);
+                    if visits < counter.limit {
+                        self.count_paths_from(next, counted, extra_visits_left, memo)
+                    } else if visits == counter.limit
+                        && counter.allows_extra_visit
+                        && extra_visits_left > 0
+                    {
+                        self.count_paths_from(next, counted, extra_visits_left - 1, memo)
+                    } else {
+                        0
+                    }
+                }
+            };
+        }
+        memo.insert((cave, visit_counts, extra_visits_left), count);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // The original breadth-first search, kept to check the memoized counter against
+    fn count_paths_by_search(map: &ConnectionMap) -> usize {
+        count_paths_with_validation(map, |path, next| {
             if let Node::SmallCave(id) = next {
                 !path.contains(&Node::SmallCave(id))
             } else {
@@ -94,11 +356,11 @@ impl<'a> ConnectionMap<'a> {
     }
 
     fn count_paths_with_validation(
-        &self,
+        map: &ConnectionMap,
         validate_movement: fn(path: &[Node], next: Node) -> bool,
     ) -> usize {
-        let mut already_explored = HashSet::<Vec<Node<'a>>>::new();
-        let mut explore_queue = VecDeque::<Vec<Node<'a>>>::new();
+        let mut already_explored = HashSet::<Vec<Node>>::new();
+        let mut explore_queue = VecDeque::<Vec<Node>>::new();
         explore_queue.push_back(vec![Node::Start]);
         let mut complete_paths: usize = 0;
         while let Some(path) = explore_queue.pop_front() {
@@ -110,7 +372,7 @@ impl<'a> ConnectionMap<'a> {
             if last_node == Node::End {
                 complete_paths += 1;
             } else {
-                if let Some(connections) = self.0.get(&last_node) {
+                if let Some(connections) = map.0.get(&last_node) {
                     for &next in connections {
                         if validate_movement(&path, next) {
                             let mut new_path = path.clone();
@@ -124,8 +386,8 @@ impl<'a> ConnectionMap<'a> {
         complete_paths
     }
 
-    fn count_paths_mk2(&self) -> usize {
-        self.count_paths_with_validation(|path, next| {
+    fn count_paths_mk2_by_search(map: &ConnectionMap) -> usize {
+        count_paths_with_validation(map, |path, next| {
             if let Node::SmallCave(new_id) = next {
                 let already_visited_times = path
                     .iter()
@@ -157,11 +419,6 @@ impl<'a> ConnectionMap<'a> {
             }
         })
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
     fn test_count_paths() {
@@ -169,7 +426,7 @@ mod tests {
             "start-A", "start-b", "A-c", "A-b", "b-d", "A-end", "b-end",
         ])
         .unwrap();
-        let result = map.count_paths();
+        let result = map.count_paths().unwrap();
         assert_eq!(result, 10);
     }
 
@@ -180,7 +437,7 @@ mod tests {
             "kj-HN", "kj-dc",
         ])
         .unwrap();
-        let result = map.count_paths();
+        let result = map.count_paths().unwrap();
         assert_eq!(result, 19);
     }
 
@@ -191,13 +448,13 @@ mod tests {
             "RW-he", "fs-DX", "pj-RW", "zg-RW", "start-pj", "he-WI", "zg-he", "pj-fs", "start-RW",
         ])
         .unwrap();
-        let result = map.count_paths();
+        let result = map.count_paths().unwrap();
         assert_eq!(result, 226);
     }
 
     #[test]
     fn part_one_answer() {
-        let result = part_one();
+        let result = part_one().unwrap();
         assert_eq!(result, 4912);
     }
 
@@ -207,7 +464,7 @@ mod tests {
             "start-A", "start-b", "A-c", "A-b", "b-d", "A-end", "b-end",
         ])
         .unwrap();
-        let result = map.count_paths_mk2();
+        let result = map.count_paths_mk2().unwrap();
         assert_eq!(result, 36);
     }
 
@@ -218,7 +475,7 @@ mod tests {
             "kj-HN", "kj-dc",
         ])
         .unwrap();
-        let result = map.count_paths_mk2();
+        let result = map.count_paths_mk2().unwrap();
         assert_eq!(result, 103);
     }
 
@@ -229,13 +486,119 @@ mod tests {
             "RW-he", "fs-DX", "pj-RW", "zg-RW", "start-pj", "he-WI", "zg-he", "pj-fs", "start-RW",
         ])
         .unwrap();
-        let result = map.count_paths_mk2();
+        let result = map.count_paths_mk2().unwrap();
         assert_eq!(result, 3509);
     }
 
+    #[test]
+    fn test_counter_matches_search() {
+        for lines in [
+            vec!["start-A", "start-b", "A-c", "A-b", "b-d", "A-end", "b-end"],
+            vec![
+                "dc-end", "HN-start", "start-kj", "dc-start", "dc-HN", "LN-dc", "HN-end", "kj-sa",
+                "kj-HN", "kj-dc",
+            ],
+            vec![
+                "fs-end", "he-DX", "fs-he", "start-DX", "pj-DX", "end-zg", "zg-sl", "zg-pj",
+                "pj-he", "RW-he", "fs-DX", "pj-RW", "zg-RW", "start-pj", "he-WI", "zg-he", "pj-fs",
+                "start-RW",
+            ],
+        ] {
+            let map = ConnectionMap::from_lines(lines).unwrap();
+            assert_eq!(map.count_paths().unwrap(), count_paths_by_search(&map));
+            assert_eq!(
+                map.count_paths_mk2().unwrap(),
+                count_paths_mk2_by_search(&map)
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_paths_dense() {
+        // every small cave connects to every other one and to both hubs
+        let caves = ["a", "b", "c", "d"];
+        let mut lines = vec![];
+        for (i, cave) in caves.iter().enumerate() {
+            lines.push(format!("start-{}", cave));
+            lines.push(format!("{}-end", cave));
+            lines.push(format!("{}-X", cave));
+            for other in caves[(i + 1)..].iter() {
+                lines.push(format!("{}-{}", cave, other));
+            }
+        }
+        let map = ConnectionMap::from_lines(lines.iter().map(|it| it.as_str())).unwrap();
+        assert_eq!(map.count_paths().unwrap(), count_paths_by_search(&map));
+        assert_eq!(
+            map.count_paths_mk2().unwrap(),
+            count_paths_mk2_by_search(&map)
+        );
+    }
+
+    #[test]
+    fn test_rules_drive_counter_and_paths() {
+        let map = ConnectionMap::from_lines([
+            "dc-end", "HN-start", "start-kj", "dc-start", "dc-HN", "LN-dc", "HN-end", "kj-sa",
+            "kj-HN", "kj-dc",
+        ])
+        .unwrap();
+        let rule_sets = [
+            VisitRules::small_caves_once(),
+            VisitRules::one_small_cave_twice(),
+            VisitRules::new(2, 0),
+            VisitRules::new(1, 2),
+            VisitRules::one_small_cave_twice()
+                .with_limit(Node::LargeCave("HN"), 1)
+                .with_limit(Node::SmallCave("dc"), 2),
+            VisitRules::one_small_cave_twice().forbid(Node::SmallCave("kj")),
+        ];
+        let counts = rule_sets
+            .iter()
+            .map(|rules| map.count_paths_with_rules(rules).unwrap())
+            .collect_vec();
+        let listed = rule_sets
+            .iter()
+            .map(|rules| map.paths(rules).count())
+            .collect_vec();
+        assert_eq!(counts, listed);
+        assert_eq!(counts[0..2], [19, 103]);
+        // forbidding a cave is the same as giving it a limit of 0
+        let limited = VisitRules::small_caves_once().with_limit(Node::SmallCave("kj"), 0);
+        let forbidden = VisitRules::small_caves_once().forbid(Node::SmallCave("kj"));
+        assert_eq!(
+            map.count_paths_with_rules(&limited),
+            map.count_paths_with_rules(&forbidden)
+        );
+    }
+
+    #[test]
+    fn test_too_many_visits_to_count() {
+        let map = ConnectionMap::from_lines(["start-a", "a-b", "b-end"]).unwrap();
+        let rules = VisitRules::new(u32::MAX, 1);
+        assert!(map.count_paths_with_rules(&rules).is_err());
+    }
+
+    #[test]
+    fn test_unlimited_cycles() {
+        let map =
+            ConnectionMap::from_lines(["start-A", "A-B", "B-end", "start-c", "c-end"]).unwrap();
+        assert_eq!(
+            map.count_paths(),
+            Err(
+                "Paths could go between A and B forever, since neither has a visit limit"
+                    .to_string()
+            )
+        );
+        let rules = VisitRules::small_caves_once().with_limit(Node::LargeCave("A"), 2);
+        // start,A,B,end and start,A,B,A,B,end, along with start,c,end
+        assert_eq!(map.count_paths_with_rules(&rules), Ok(3));
+
+        let map = ConnectionMap::from_lines(["start-A", "A-A", "A-end"]).unwrap();
+        assert!(map.count_paths().is_err());
+    }
+
     #[test]
     fn part_two_answer() {
-        let result = part_two();
+        let result = part_two().unwrap();
         assert_eq!(result, 150004);
     }
 }
@@ -0,0 +1,211 @@
+use super::{ConnectionMap, Node, VisitRules};
+use crate::prelude::*;
+
+/// Every path from start to end, found one at a time with a depth-first search
+pub struct Paths<'m, 'a> {
+    map: &'m ConnectionMap<'a>,
+    rules: &'m VisitRules<'a>,
+    stack: Vec<Vec<Node<'a>>>,
+}
+
+impl<'m, 'a> Iterator for Paths<'m, 'a> {
+    type Item = Vec<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(path) = self.stack.pop() {
+            let last_node = *path.last().unwrap();
+            if last_node == Node::End {
+                return Some(path);
+            }
+            if let Some(connections) = self.map.0.get(&last_node) {
+                // reversed so that connections get explored in the order they were listed
+                for &next in connections.iter().rev() {
+                    if self.rules.allows_move(&path, next) {
+                        let mut new_path = path.clone();
+                        new_path.push(next);
+                        self.stack.push(new_path);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'m, 'a: 'm> Paths<'m, 'a> {
+    pub fn through(self, cave: Node<'a>) -> impl Iterator<Item = Vec<Node<'a>>> + 'm {
+        self.filter(move |path| path.contains(&cave))
+    }
+
+    pub fn avoiding(self, cave: Node<'a>) -> impl Iterator<Item = Vec<Node<'a>>> + 'm {
+        self.filter(move |path| !path.contains(&cave))
+    }
+}
+
+impl<'a> Node<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Node::Start => "start",
+            Node::End => "end",
+            Node::SmallCave(name) | Node::LargeCave(name) => name,
+        }
+    }
+}
+
+/// Formats a path the way the puzzle lists them, like `start,A,b,end`
+pub fn format_path(path: &[Node]) -> String {
+    path.iter().map(|node| node.name()).join(",")
+}
+
+impl<'a> ConnectionMap<'a> {
+    pub fn paths<'m>(&'m self, rules: &'m VisitRules<'a>) -> Paths<'m, 'a> {
+        Paths {
+            map: self,
+            rules,
+            stack: vec![vec![Node::Start]],
+        }
+    }
+
+    /// Draws the caves as a Graphviz graph
+    pub fn to_dot(&self) -> String {
+        let connections = self
+            .0
+            .iter()
+            .flat_map(|(&from, to)| to.iter().map(move |&to| (from.min(to), from.max(to))))
+            .unique()
+            .sorted()
+            .collect_vec();
+        let nodes = connections
+            .iter()
+            .flat_map(|&(a, b)| [a, b])
+            .unique()
+            .sorted()
+            .collect_vec();
+
+        let mut lines = vec!["graph caves {".to_string()];
+        for node in nodes {
+            let style = match node {
+                Node::Start => "shape=doublecircle, style=filled, fillcolor=palegreen",
+                Node::End => "shape=doublecircle, style=filled, fillcolor=lightpink",
+                Node::SmallCave(_) => "shape=circle",
+                Node::LargeCave(_) => "shape=box, style=bold",
+            };
+            lines.push(format!("  \"{}\" [{}];", node.name(), style));
+        }
+        for (a, b) in connections {
+            lines.push(format!("  \"{}\" -- \"{}\";", a.name(), b.name()));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    lazy_static! {
+        static ref EXAMPLE_MAP: ConnectionMap<'static> = ConnectionMap::from_lines([
+            "start-A", "start-b", "A-c", "A-b", "b-d", "A-end", "b-end",
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn test_paths() {
+        let paths = EXAMPLE_MAP
+            .paths(&VisitRules::small_caves_once())
+            .map(|path| format_path(&path))
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            paths,
+            vec![
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+        );
+        assert_eq!(paths.len(), EXAMPLE_MAP.count_paths().unwrap());
+    }
+
+    #[test]
+    fn test_paths_are_lazy() {
+        // a chain of diamonds, each of which doubles the number of paths
+        const STAGES: usize = 40;
+        let lines = (1..=STAGES)
+            .flat_map(|i| {
+                let previous = if i == 1 {
+                    "start".to_string()
+                } else {
+                    format!("c{}", i - 1)
+                };
+                [
+                    format!("{}-a{}", previous, i),
+                    format!("{}-b{}", previous, i),
+                    format!("a{}-c{}", i, i),
+                    format!("b{}-c{}", i, i),
+                ]
+            })
+            .chain([format!("c{}-end", STAGES)])
+            .collect_vec();
+        let map = ConnectionMap::from_lines(lines.iter().map(|it| it.as_str())).unwrap();
+        let rules = VisitRules::small_caves_once();
+
+        let mut paths = map.paths(&rules);
+        let first = paths.next().unwrap();
+        let expected = std::iter::once("start".to_string())
+            .chain((1..=STAGES).flat_map(|i| [format!("a{}", i), format!("c{}", i)]))
+            .chain(["end".to_string()])
+            .join(",");
+        assert_eq!(format_path(&first), expected);
+        // only the branches along the way are waiting to be explored, not the rest of the paths
+        assert!(paths.stack.len() <= 3 * STAGES);
+
+        let first = EXAMPLE_MAP.paths(&rules).next().unwrap();
+        assert_eq!(format_path(&first), "start,A,c,A,b,A,end");
+    }
+
+    #[test]
+    fn test_filter_paths() {
+        let through_c = EXAMPLE_MAP
+            .paths(&VisitRules::small_caves_once())
+            .through(Node::SmallCave("c"))
+            .count();
+        assert_eq!(through_c, 5);
+        let avoiding_a = EXAMPLE_MAP
+            .paths(&VisitRules::small_caves_once())
+            .avoiding(Node::LargeCave("A"))
+            .map(|path| format_path(&path))
+            .collect_vec();
+        assert_eq!(avoiding_a, vec!["start,b,end"]);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let map = ConnectionMap::from_lines(["start-A", "A-b", "b-end", "A-end"]).unwrap();
+        assert_eq!(
+            map.to_dot(),
+            [
+                "graph caves {",
+                "  \"start\" [shape=doublecircle, style=filled, fillcolor=palegreen];",
+                "  \"end\" [shape=doublecircle, style=filled, fillcolor=lightpink];",
+                "  \"b\" [shape=circle];",
+                "  \"A\" [shape=box, style=bold];",
+                "  \"start\" -- \"A\";",
+                "  \"end\" -- \"b\";",
+                "  \"end\" -- \"A\";",
+                "  \"b\" -- \"A\";",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+}
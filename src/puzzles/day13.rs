@@ -1,7 +1,11 @@
 // Day 13: Transparent Origami
+mod ocr;
+pub mod unfold;
+
 use crate::prelude::*;
 use crate::shared::grid::{HashGrid, Point};
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::str::FromStr;
 
@@ -10,47 +14,80 @@ lazy_static! {
         Instructions::from_lines(include_lines!("day13_input.txt")).unwrap();
 }
 
-pub fn part_one() -> usize {
-    PUZZLE_INPUT
+pub fn part_one() -> Result<usize, String> {
+    Ok(PUZZLE_INPUT
         .grid
-        .fold(PUZZLE_INPUT.fold_instructions[0])
-        .count_dots()
+        .fold(PUZZLE_INPUT.fold_instructions[0])?
+        .count_dots())
 }
 
-pub fn part_two() -> String {
-    PUZZLE_INPUT.follow_instructions().to_string()
+pub fn part_two() -> Result<String, String> {
+    PUZZLE_INPUT.follow_instructions()?.read_letters()
 }
 
 #[derive(Clone)]
-struct OrigamiGrid(HashGrid<()>);
+pub struct OrigamiGrid(HashGrid<()>);
+
+/// A fold along one axis of a sheet that's `length` dots long, bringing the far side over
+/// onto the side nearer the origin
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Crease {
+    pub line: usize,
+    pub length: usize,
+}
+
+impl Crease {
+    /// How far the far side sticks out past the near side's edge once it's folded over.
+    /// Everything gets shifted over by this much so that no coordinates end up negative.
+    pub fn overhang(&self) -> usize {
+        self.length.saturating_sub(1).saturating_sub(2 * self.line)
+    }
+
+    pub fn folded_length(&self) -> usize {
+        self.line + self.overhang()
+    }
+
+    /// `None` for a coordinate right on the fold line
+    pub fn fold(&self, coordinate: usize) -> Option<usize> {
+        match coordinate.cmp(&self.line) {
+            Ordering::Less => Some(coordinate + self.overhang()),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(2 * self.line + self.overhang() - coordinate),
+        }
+    }
+}
 
 impl OrigamiGrid {
-    fn fold(&self, instruction: FoldInstruction) -> OrigamiGrid {
+    fn crease(&self, instruction: FoldInstruction) -> Crease {
+        let layout = self.0.layout();
+        match instruction {
+            FoldInstruction::X(line) => Crease {
+                line,
+                length: layout.width,
+            },
+            FoldInstruction::Y(line) => Crease {
+                line,
+                length: layout.height,
+            },
+        }
+    }
+
+    pub fn fold(&self, instruction: FoldInstruction) -> Result<OrigamiGrid, String> {
+        let crease = self.crease(instruction);
         let mut new_grid = OrigamiGrid(HashGrid::new());
         for (point, _) in self.0.all_extant_points() {
             let Point { x, y } = point;
             let new_point = match instruction {
-                FoldInstruction::X(fold_x) => {
-                    if x > fold_x {
-                        Point::new(2 * fold_x - x, y)
-                    } else {
-                        point
-                    }
-                }
-                FoldInstruction::Y(fold_y) => {
-                    if y > fold_y {
-                        Point::new(x, 2 * fold_y - y)
-                    } else {
-                        point
-                    }
-                }
-            };
+                FoldInstruction::X(_) => crease.fold(x).map(|x| Point::new(x, y)),
+                FoldInstruction::Y(_) => crease.fold(y).map(|y| Point::new(x, y)),
+            }
+            .ok_or(format!("There's a dot on the fold line at {},{}", x, y))?;
             new_grid.0.set(new_point, ());
         }
-        new_grid
+        Ok(new_grid)
     }
 
-    fn count_dots(&self) -> usize {
+    pub fn count_dots(&self) -> usize {
         self.0.all_extant_points().count()
     }
 }
@@ -84,7 +121,7 @@ impl Display for OrigamiGrid {
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-enum FoldInstruction {
+pub enum FoldInstruction {
     X(usize),
     Y(usize),
 }
@@ -105,13 +142,15 @@ impl FromStr for FoldInstruction {
     }
 }
 
-struct Instructions {
+pub struct Instructions {
     grid: OrigamiGrid,
     fold_instructions: Vec<FoldInstruction>,
 }
 
 impl Instructions {
-    fn from_lines<'a, T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Instructions, String> {
+    pub fn from_lines<'a, T: IntoIterator<Item = &'a str>>(
+        iter: T,
+    ) -> Result<Instructions, String> {
         let mut iter = iter.into_iter();
         let points = (&mut iter)
             .take_while(|it| !it.is_empty())
@@ -127,13 +166,13 @@ impl Instructions {
         })
     }
 
-    fn follow_instructions(&self) -> OrigamiGrid {
+    pub fn follow_instructions(&self) -> Result<OrigamiGrid, String> {
         self.fold_instructions
             .iter()
-            .fold(Cow::Borrowed(&self.grid), |grid, instruction| {
-                Cow::Owned(grid.fold(*instruction))
+            .try_fold(Cow::Borrowed(&self.grid), |grid, instruction| {
+                Ok(Cow::Owned(grid.fold(*instruction)?))
             })
-            .into_owned()
+            .map(|grid| grid.into_owned())
     }
 }
 
@@ -179,27 +218,45 @@ mod tests {
 
     #[test]
     fn test_fold() {
-        let result = EXAMPLE_INPUT.grid.fold(EXAMPLE_INPUT.fold_instructions[0]);
+        let result = EXAMPLE_INPUT
+            .grid
+            .fold(EXAMPLE_INPUT.fold_instructions[0])
+            .unwrap();
         assert_eq!(result.count_dots(), 17);
     }
 
+    #[test]
+    fn test_fold_before_midpoint() {
+        // the right side is longer, so it sticks out past where the left edge used to be
+        let grid: OrigamiGrid = [Point::new(0, 0), Point::new(3, 0), Point::new(5, 1)]
+            .into_iter()
+            .collect();
+        let result = grid.fold(FoldInstruction::X(1)).unwrap();
+        assert_eq!(result.to_string(), ["..##", "#..."].join("\n"));
+    }
+
+    #[test]
+    fn test_dot_on_fold_line() {
+        let result = EXAMPLE_INPUT.grid.fold(FoldInstruction::Y(10));
+        assert!(result.is_err());
+    }
+
     #[test]
     fn part_one_answer() {
-        let result = part_one();
+        let result = part_one().unwrap();
         assert_eq!(result, 710);
     }
 
     #[test]
     fn test_display() {
         let expected = ["#####", "#...#", "#...#", "#...#", "#####"].join("\n");
-        let result = EXAMPLE_INPUT.follow_instructions();
+        let result = EXAMPLE_INPUT.follow_instructions().unwrap();
         assert_eq!(result.to_string(), expected);
     }
 
     #[test]
     fn part_two_answer() {
-        let expected = include_str!("day13_answer.txt").trim_end();
-        let result = part_two();
-        assert_eq!(result.as_str(), expected);
+        let result = part_two().unwrap();
+        assert_eq!(result, "EPLGRULR");
     }
 }
@@ -0,0 +1,262 @@
+use super::OrigamiGrid;
+use crate::prelude::*;
+use crate::shared::grid::Point;
+
+/// Capital letters drawn with `#` and `.`, laid out next to each other with a gap between them
+struct Font {
+    width: usize,
+    height: usize,
+    spacing: usize,
+    letters: &'static [(char, &'static [&'static str])],
+}
+
+/// The font the thermal camera manual uses, and the one for most other puzzles with letters in them
+const SMALL_FONT: Font = Font {
+    width: 4,
+    height: 6,
+    spacing: 1,
+    letters: &[
+        ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+        ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+        ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+        ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+        ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+        ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+        ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+        ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+        ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+        ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+        ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+        ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+        ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+        ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+        ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ],
+};
+
+/// The taller font some older puzzles draw their messages with
+const LARGE_FONT: Font = Font {
+    width: 6,
+    height: 10,
+    spacing: 2,
+    letters: &[
+        (
+            'A',
+            &[
+                "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'B',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+                "#....#", "#####.",
+            ],
+        ),
+        (
+            'C',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#....#", ".####.",
+            ],
+        ),
+        (
+            'E',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'F',
+            &[
+                "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'G',
+            &[
+                ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+                "#...##", ".###.#",
+            ],
+        ),
+        (
+            'H',
+            &[
+                "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'J',
+            &[
+                "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+                "#...#.", ".###..",
+            ],
+        ),
+        (
+            'K',
+            &[
+                "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+                "#...#.", "#....#",
+            ],
+        ),
+        (
+            'L',
+            &[
+                "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+        (
+            'N',
+            &[
+                "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+                "#...##", "#....#",
+            ],
+        ),
+        (
+            'P',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+                "#.....", "#.....",
+            ],
+        ),
+        (
+            'R',
+            &[
+                "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'X',
+            &[
+                "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+                "#....#", "#....#",
+            ],
+        ),
+        (
+            'Z',
+            &[
+                "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+                "#.....", "######",
+            ],
+        ),
+    ],
+};
+
+impl OrigamiGrid {
+    fn row_text(&self, y: usize, xs: std::ops::Range<usize>) -> String {
+        xs.map(|x| match self.0.get(Point::new(x, y)) {
+            Some(()) => '#',
+            None => '.',
+        })
+        .collect()
+    }
+
+    /// Reads the code drawn by the dots, picking the font that's as tall as the grid
+    pub fn read_letters(&self) -> Result<String, String> {
+        let layout = self.0.layout();
+        let font = [SMALL_FONT, LARGE_FONT]
+            .into_iter()
+            .find(|font| font.height == layout.height)
+            .ok_or(format!(
+                "No font is {} dots tall; letters need to be {} or {} dots tall",
+                layout.height, SMALL_FONT.height, LARGE_FONT.height
+            ))?;
+
+        let pitch = font.width + font.spacing;
+        let letter_count = layout.width.div_ceil(pitch);
+        (0..letter_count)
+            .map(|i| {
+                let left = i * pitch;
+                let glyph = (0..font.height)
+                    .map(|y| self.row_text(y, left..(left + font.width)))
+                    .collect_vec();
+                let gap_is_empty = (0..font.height).all(|y| {
+                    self.row_text(y, (left + font.width)..(left + pitch))
+                        .chars()
+                        .all(|c| c == '.')
+                });
+                if !gap_is_empty {
+                    return Err(format!("Letter at column {} runs into the next one", left));
+                }
+                font.letters
+                    .iter()
+                    .find(|(_, rows)| *rows == glyph.as_slice())
+                    .map(|(letter, _)| *letter)
+                    .ok_or(format!(
+                        "Unrecognized letter at column {}:\n{}",
+                        left,
+                        glyph.join("\n")
+                    ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_from_art(art: &str) -> OrigamiGrid {
+        art.lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| Point::new(x, y))
+            })
+            .collect()
+    }
+
+    fn draw(font: &Font, text: &str) -> String {
+        let gap = ".".repeat(font.spacing);
+        (0..font.height)
+            .map(|y| {
+                text.chars()
+                    .map(|c| {
+                        let (_, rows) = font.letters.iter().find(|(it, _)| *it == c).unwrap();
+                        rows[y]
+                    })
+                    .join(&gap)
+            })
+            .join("\n")
+    }
+
+    #[test]
+    fn test_read_answer() {
+        let grid = grid_from_art(include_str!("../day13_answer.txt"));
+        assert_eq!(grid.read_letters(), Ok("EPLGRULR".to_string()));
+    }
+
+    #[test]
+    fn test_read_every_letter() {
+        for font in [SMALL_FONT, LARGE_FONT] {
+            let alphabet: String = font.letters.iter().map(|(letter, _)| letter).collect();
+            let grid = grid_from_art(&draw(&font, &alphabet));
+            assert_eq!(grid.read_letters(), Ok(alphabet));
+        }
+    }
+
+    #[test]
+    fn test_unrecognized_letter() {
+        let grid = grid_from_art(&["#..#", ".##.", "#..#", "#..#", "#..#", "#..#"].join("\n"));
+        assert!(grid
+            .read_letters()
+            .unwrap_err()
+            .starts_with("Unrecognized letter at column 0"));
+    }
+
+    #[test]
+    fn test_wrong_height() {
+        let grid = grid_from_art(&["#####", "#...#", "#...#", "#...#", "#####"].join("\n"));
+        assert!(grid.read_letters().is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use super::{Crease, FoldInstruction, Instructions, OrigamiGrid};
+use crate::prelude::*;
+use crate::shared::grid::{HashGrid, Point};
+use std::borrow::Cow;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FoldDirection {
+    /// Up or left, like the puzzle's instructions
+    TowardsOrigin,
+    /// Down or right, with the coordinates starting again just past the fold line
+    AwayFromOrigin,
+}
+
+impl Crease {
+    /// Every coordinate on the unfolded sheet that folds onto this one
+    pub fn unfold(&self, coordinate: usize) -> Vec<usize> {
+        if coordinate >= self.folded_length() {
+            return vec![];
+        }
+        let overhang = self.overhang();
+        let near_side = coordinate
+            .checked_sub(overhang)
+            .filter(|&it| it < self.line);
+        let far_side = Some(2 * self.line + overhang - coordinate)
+            .filter(|&it| it > self.line && it < self.length);
+        near_side.into_iter().chain(far_side).collect()
+    }
+}
+
+impl FoldInstruction {
+    fn with_line(&self, line: usize) -> FoldInstruction {
+        match self {
+            FoldInstruction::X(_) => FoldInstruction::X(line),
+            FoldInstruction::Y(_) => FoldInstruction::Y(line),
+        }
+    }
+}
+
+impl OrigamiGrid {
+    /// Flips the sheet over along the axis the instruction folds, as if it was `length` dots long
+    fn flipped(&self, instruction: FoldInstruction, length: usize) -> OrigamiGrid {
+        let mut new_grid = OrigamiGrid(HashGrid::new());
+        for (Point { x, y }, _) in self.0.all_extant_points() {
+            let new_point = match instruction {
+                FoldInstruction::X(_) => Point::new(length - 1 - x, y),
+                FoldInstruction::Y(_) => Point::new(x, length - 1 - y),
+            };
+            new_grid.0.set(new_point, ());
+        }
+        new_grid
+    }
+
+    pub fn fold_towards(
+        &self,
+        instruction: FoldInstruction,
+        direction: FoldDirection,
+    ) -> Result<OrigamiGrid, String> {
+        match direction {
+            FoldDirection::TowardsOrigin => self.fold(instruction),
+            FoldDirection::AwayFromOrigin => {
+                // folding the other way is the same as flipping the sheet over first
+                let crease = self.crease(instruction);
+                if crease.line >= crease.length {
+                    return Err(format!(
+                        "Folding along {} would leave nothing on the far side",
+                        crease.line
+                    ));
+                }
+                let flipped_crease = Crease {
+                    line: crease.length - 1 - crease.line,
+                    length: crease.length,
+                };
+                Ok(self
+                    .flipped(instruction, crease.length)
+                    .fold(instruction.with_line(flipped_crease.line))?
+                    .flipped(instruction, flipped_crease.folded_length()))
+            }
+        }
+    }
+}
+
+impl Instructions {
+    /// The crease each instruction folds along, measured on the sheet as it was when that
+    /// fold was made, so that unfolding lines up with folding
+    fn creases(&self) -> Result<Vec<(FoldInstruction, Crease)>, String> {
+        let mut grid = Cow::Borrowed(&self.grid);
+        let mut creases = vec![];
+        for &instruction in self.fold_instructions.iter() {
+            creases.push((instruction, grid.crease(instruction)));
+            grid = Cow::Owned(grid.fold(instruction)?);
+        }
+        Ok(creases)
+    }
+
+    /// Every dot on the unfolded sheet that ends up on `point` once all the instructions
+    /// have been followed
+    pub fn unfold(&self, point: Point) -> Result<Vec<Point>, String> {
+        let creases = self.creases()?;
+        Ok(creases
+            .iter()
+            .rev()
+            .fold(vec![point], |points, (instruction, crease)| {
+                points
+                    .into_iter()
+                    .flat_map(|Point { x, y }| match instruction {
+                        FoldInstruction::X(_) => crease
+                            .unfold(x)
+                            .into_iter()
+                            .map(|x| Point::new(x, y))
+                            .collect_vec(),
+                        FoldInstruction::Y(_) => crease
+                            .unfold(y)
+                            .into_iter()
+                            .map(|y| Point::new(x, y))
+                            .collect_vec(),
+                    })
+                    .collect()
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_away_from_origin() {
+        let grid: OrigamiGrid = [Point::new(0, 0), Point::new(2, 0), Point::new(4, 1)]
+            .into_iter()
+            .collect();
+        let result = grid
+            .fold_towards(FoldInstruction::X(1), FoldDirection::AwayFromOrigin)
+            .unwrap();
+        assert_eq!(result.to_string(), ["#..", "..#"].join("\n"));
+    }
+
+    #[test]
+    fn test_unfold_crease() {
+        let crease = Crease {
+            line: 7,
+            length: 15,
+        };
+        assert_eq!(crease.unfold(0), vec![0, 14]);
+        assert_eq!(crease.unfold(7), vec![]);
+        // the far side is longer than the near side
+        let crease = Crease { line: 1, length: 6 };
+        assert_eq!(crease.unfold(0), vec![5]);
+        assert_eq!(crease.unfold(3), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_unfold() {
+        let instructions =
+            Instructions::from_lines(["0,0", "10,14", "", "fold along y=7", "fold along x=5"])
+                .unwrap();
+        let points = instructions.unfold(Point::new(0, 0)).unwrap();
+        assert_eq!(
+            points
+                .into_iter()
+                .sorted_by_key(|it| (it.y, it.x))
+                .collect_vec(),
+            vec![
+                Point::new(0, 0),
+                Point::new(10, 0),
+                Point::new(0, 14),
+                Point::new(10, 14)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unfold_reverses_fold() {
+        let instructions = Instructions::from_lines([
+            "6,10",
+            "0,14",
+            "9,10",
+            "0,3",
+            "10,4",
+            "4,11",
+            "6,0",
+            "6,12",
+            "4,1",
+            "0,13",
+            "10,12",
+            "3,4",
+            "3,0",
+            "8,4",
+            "1,10",
+            "2,14",
+            "8,10",
+            "9,0",
+            "",
+            "fold along y=7",
+            "fold along x=5",
+        ])
+        .unwrap();
+        let folded = instructions.follow_instructions().unwrap();
+        let unfolded = folded
+            .0
+            .all_extant_points()
+            .flat_map(|(point, _)| instructions.unfold(point).unwrap())
+            .collect_vec();
+        for (point, _) in instructions.grid.0.all_extant_points() {
+            assert!(unfolded.contains(&point));
+        }
+    }
+
+    #[test]
+    fn test_unfold_matches_fold() {
+        // the first fold leaves the sheet narrower than the crease alone would suggest,
+        // since nothing ends up past x=2
+        let instructions =
+            Instructions::from_lines(["0,0", "2,0", "6,0", "", "fold along x=4", "fold along x=1"])
+                .unwrap();
+        let folded = instructions.follow_instructions().unwrap();
+        assert_eq!(folded.to_string(), "#");
+        let unfolded = instructions
+            .unfold(Point::new(0, 0))
+            .unwrap()
+            .into_iter()
+            .sorted_by_key(|it| (it.y, it.x))
+            .collect_vec();
+        let original = instructions
+            .grid
+            .0
+            .all_extant_points()
+            .map(|(point, _)| point)
+            .sorted_by_key(|it| (it.y, it.x))
+            .collect_vec();
+        assert_eq!(unfolded, original);
+    }
+}
@@ -1,9 +1,10 @@
-use std::{
-    borrow::Cow,
-    collections::{hash_map::Entry, HashMap, HashSet},
-};
+use std::{borrow::Cow, collections::HashMap};
 
 // Day 0: Template
+pub mod access;
+pub mod analysis;
+mod transitions;
+
 use crate::prelude::*;
 
 lazy_static! {
@@ -15,43 +16,52 @@ pub fn part_one() -> u64 {
     frequency_score(&polymer)
 }
 
-pub fn part_two() -> u64 {
-    let frequency_map = PUZZLE_INPUT.simulate_steps(40);
-    frequency_score_from_map(&frequency_map)
+pub fn part_two() -> Result<u64, String> {
+    let frequency_map = PUZZLE_INPUT.simulate_steps(40)?;
+    Ok(frequency_score_from_map(&frequency_map))
 }
 
 type CharPair = (char, char);
+type InsertionRule = (CharPair, char);
 type FrequencyMap = HashMap<char, u64>;
 
-struct Input {
+pub struct Input {
     polymer: Vec<char>,
     insertion_rules: HashMap<CharPair, char>,
 }
 
+/// The template and every rule, in the order they were listed
+fn parse_lines<'a, T: IntoIterator<Item = &'a str>>(
+    iter: T,
+) -> Result<(Vec<char>, Vec<InsertionRule>), String> {
+    let mut iter = iter.into_iter();
+    let polymer = iter.next().ok_or("Empty file")?.chars().collect();
+    match iter.next() {
+        Some("") => (),
+        _ => return Err("Expected empty line after polymer template".to_string()),
+    }
+    let insertion_rules = iter
+        .map(|line| {
+            let (pair, insert) = line.split_once(" -> ").ok_or("Expected ->".to_string())?;
+            let pair = pair.chars().collect_vec();
+            let insert = insert.chars().collect_vec();
+            if pair.len() != 2 || insert.len() != 1 {
+                return Err(format!("bad pair insertion rule: {}", line));
+            }
+            let pair = (pair[0], pair[1]);
+            let insert = insert[0];
+            Ok((pair, insert))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((polymer, insertion_rules))
+}
+
 impl Input {
-    fn from_lines<'a, T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Self, String> {
-        let mut iter = iter.into_iter();
-        let polymer = iter.next().ok_or("Empty file")?.chars().collect();
-        match iter.next() {
-            Some("") => (),
-            _ => return Err("Expected empty line after polymer template".to_string()),
-        }
-        let insertion_rules = iter
-            .map(|line| {
-                let (pair, insert) = line.split_once(" -> ").ok_or("Expected ->".to_string())?;
-                let pair = pair.chars().collect_vec();
-                let insert = insert.chars().collect_vec();
-                if pair.len() != 2 || insert.len() != 1 {
-                    return Err(format!("bad pair insertion rule: {}", line));
-                }
-                let pair = (pair[0], pair[1]);
-                let insert = insert[0];
-                Ok((pair, insert))
-            })
-            .collect::<Result<HashMap<_, _>, _>>()?;
+    pub fn from_lines<'a, T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Self, String> {
+        let (polymer, insertion_rules) = parse_lines(iter)?;
         Ok(Input {
             polymer,
-            insertion_rules,
+            insertion_rules: insertion_rules.into_iter().collect(),
         })
     }
 
@@ -73,82 +83,11 @@ impl Input {
             .into_owned()
     }
 
-    fn simulate_steps(&self, count: usize) -> FrequencyMap {
-        let mut memo = MemoizedPolymerExpander::new(self);
-        let starting_map: FrequencyMap = self
-            .polymer
-            .iter()
-            .copied()
-            .counts()
-            .into_iter()
-            .map(|(char, count)| (char, count as u64))
-            .collect();
-
-        self.polymer
-            .windows(2)
-            .map(|window| -> FrequencyMap { memo.expand_polymer((window[0], window[1]), count) })
-            .fold(starting_map, |prev, next| add_frequency_maps(&prev, &next))
-    }
-}
-
-struct MemoizedPolymerExpander<'a> {
-    memos: HashMap<(CharPair, usize), FrequencyMap>,
-    input: &'a Input,
-}
-
-impl<'a> MemoizedPolymerExpander<'a> {
-    fn new(input: &'a Input) -> Self {
-        MemoizedPolymerExpander {
-            memos: HashMap::new(),
-            input,
-        }
-    }
-
-    fn expand_polymer(&mut self, pair: CharPair, remaining_count: usize) -> FrequencyMap {
-        if remaining_count == 0 {
-            return FrequencyMap::new();
-        }
-
-        let key = (pair, remaining_count);
-        if let Some(memoized_result) = self.memos.get(&key) {
-            return memoized_result.to_owned();
-        }
-
-        if let Some(&inserted_char) = self.input.insertion_rules.get(&pair) {
-            let a = self.expand_polymer((pair.0, inserted_char), remaining_count - 1);
-            let b = self.expand_polymer((inserted_char, pair.1), remaining_count - 1);
-
-            let mut result = add_frequency_maps(&a, &b);
-            match result.entry(inserted_char) {
-                Entry::Occupied(mut entry) => {
-                    *entry.get_mut() += 1;
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert(1);
-                }
-            }
-
-            self.memos.insert(key, result.clone());
-            result
-        } else {
-            FrequencyMap::new()
-        }
+    fn simulate_steps(&self, count: usize) -> Result<FrequencyMap, String> {
+        self.element_counts_after(count as u64)
     }
 }
 
-fn add_frequency_maps(a: &FrequencyMap, b: &FrequencyMap) -> FrequencyMap {
-    let all_keys: HashSet<char> = a.keys().chain(b.keys()).copied().collect();
-    all_keys
-        .into_iter()
-        .map(|char| {
-            (
-                char,
-                a.get(&char).unwrap_or(&0) + b.get(&char).unwrap_or(&0),
-            )
-        })
-        .collect()
-}
-
 fn frequency_score(input: &[char]) -> u64 {
     let frequency_map = input
         .iter()
@@ -223,14 +162,14 @@ mod tests {
 
     #[test]
     fn test_bigger() {
-        let frequency_map = EXAMPLE_INPUT.simulate_steps(40);
+        let frequency_map = EXAMPLE_INPUT.simulate_steps(40).unwrap();
         let result = frequency_score_from_map(&frequency_map);
         assert_eq!(result, 2188189693529);
     }
 
     #[test]
     fn part_two_answer() {
-        let result = part_two();
+        let result = part_two().unwrap();
         assert_eq!(result, 10002813279337);
     }
 }
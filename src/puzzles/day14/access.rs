@@ -0,0 +1,333 @@
+use super::transitions::PairTransitions;
+use super::Input;
+use crate::prelude::*;
+use std::ops::Range;
+
+/// How many steps' worth of lengths get worked out one at a time.
+/// Polymers that double in length become too long to index long before this,
+/// and ones that stop growing don't need any more than it took them to stop.
+const MAX_TABULATED_STEPS: usize = 4096;
+
+/// The polymer after some number of steps, which can be read from anywhere
+/// without building the whole thing
+pub struct ExpandedPolymer {
+    transitions: PairTransitions,
+    steps: u64,
+    /// `lengths[depth][pair]` is how long a pair gets after `depth` steps, not counting its
+    /// second element, which belongs to the next pair.
+    /// Lengths that don't fit stop at `u64::MAX`, but those pairs are never part of the polymer.
+    /// Once the lengths stop changing, the last row stands in for every step after it.
+    lengths: Vec<Vec<u64>>,
+    len: u64,
+}
+
+/// An index where two polymers have different elements,
+/// with `None` for a polymer that's already ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difference {
+    pub index: u64,
+    pub ours: Option<char>,
+    pub theirs: Option<char>,
+}
+
+impl Input {
+    /// Fails if the polymer gets too long to index with a `u64`,
+    /// or is still growing after more steps than can be tabulated
+    pub fn expanded(&self, steps: u64) -> Result<ExpandedPolymer, String> {
+        let transitions = PairTransitions::new(self);
+        let too_long = || format!("The polymer is too long to index after {} steps", steps);
+        let polymer_length = |layer: &[u64]| {
+            transitions
+                .initial_pairs
+                .iter()
+                .try_fold(0u64, |sum, &pair| sum.checked_add(layer[pair]))
+                .and_then(|length| match transitions.last_element {
+                    Some(_) => length.checked_add(1),
+                    None => Some(0),
+                })
+        };
+
+        let mut lengths = vec![vec![1; transitions.pairs.len()]];
+        let mut len = polymer_length(&lengths[0]).ok_or_else(too_long)?;
+        while (lengths.len() as u64) <= steps {
+            let previous = lengths.last().unwrap();
+            let next = transitions
+                .produces
+                .iter()
+                .map(|produced| {
+                    produced
+                        .iter()
+                        .fold(0u64, |sum, &pair| sum.saturating_add(previous[pair]))
+                })
+                .collect_vec();
+            if &next == previous {
+                // nothing grows any more, so every step from here on looks the same
+                break;
+            }
+            if lengths.len() > MAX_TABULATED_STEPS {
+                return Err(format!(
+                    "The polymer is still growing after {} steps, so it can't be read after {}",
+                    MAX_TABULATED_STEPS, steps
+                ));
+            }
+            len = polymer_length(&next).ok_or_else(too_long)?;
+            lengths.push(next);
+        }
+        Ok(ExpandedPolymer {
+            transitions,
+            steps,
+            lengths,
+            len,
+        })
+    }
+}
+
+impl ExpandedPolymer {
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn length(&self, depth: u64, pair: usize) -> u64 {
+        let last = self.lengths.len() - 1;
+        self.lengths[(depth.min(last as u64)) as usize][pair]
+    }
+
+    /// Pairs without an insertion rule stay the same no matter how many steps go by
+    fn is_settled(&self, pair: usize) -> bool {
+        self.transitions.produces[pair][..] == [pair]
+    }
+
+    pub fn char_at(&self, index: u64) -> Result<char, String> {
+        let mut remaining = index;
+        for &pair in self.transitions.initial_pairs.iter() {
+            let length = self.length(self.steps, pair);
+            if remaining < length {
+                return Ok(self.char_in_pair(pair, self.steps, remaining));
+            }
+            remaining -= length;
+        }
+        match self.transitions.last_element {
+            Some(last) if remaining == 0 => Ok(last),
+            _ => Err(format!(
+                "Index {} is past the end of the polymer, which is {} long",
+                index,
+                self.len()
+            )),
+        }
+    }
+
+    fn char_in_pair(&self, mut pair: usize, mut depth: u64, mut index: u64) -> char {
+        while depth > 0 && !self.is_settled(pair) {
+            for &next in self.transitions.produces[pair].iter() {
+                let length = self.length(depth - 1, next);
+                if index < length {
+                    pair = next;
+                    break;
+                }
+                index -= length;
+            }
+            depth -= 1;
+        }
+        self.transitions.pairs[pair].0
+    }
+
+    /// The elements in `range`, only expanding the pairs that overlap it
+    pub fn substring(&self, range: Range<u64>) -> Result<String, String> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(format!(
+                "Can't take {:?} out of a polymer that's {} long",
+                range,
+                self.len()
+            ));
+        }
+        let mut result = String::new();
+        let mut start = 0u64;
+        for &pair in self.transitions.initial_pairs.iter() {
+            self.write_pair(pair, self.steps, start, &range, &mut result);
+            start += self.length(self.steps, pair);
+        }
+        if let Some(last) = self.transitions.last_element {
+            if range.contains(&start) {
+                result.push(last);
+            }
+        }
+        Ok(result)
+    }
+
+    fn write_pair(
+        &self,
+        pair: usize,
+        depth: u64,
+        start: u64,
+        range: &Range<u64>,
+        result: &mut String,
+    ) {
+        let end = start + self.length(depth, pair);
+        if end <= range.start || start >= range.end {
+            return;
+        }
+        if depth == 0 || self.is_settled(pair) {
+            result.push(self.transitions.pairs[pair].0);
+            return;
+        }
+        let mut child_start = start;
+        for &next in self.transitions.produces[pair].iter() {
+            self.write_pair(next, depth - 1, child_start, range, result);
+            child_start += self.length(depth - 1, next);
+        }
+    }
+
+    /// Every index in `range` where the two polymers have different elements
+    pub fn differences(
+        &self,
+        other: &ExpandedPolymer,
+        range: Range<u64>,
+    ) -> Result<Vec<Difference>, String> {
+        let clamp = |polymer: &ExpandedPolymer| {
+            let end = range.end.min(polymer.len());
+            polymer.substring(range.start.min(end)..end)
+        };
+        let ours = clamp(self)?.chars().collect::<Vec<_>>();
+        let theirs = clamp(other)?.chars().collect::<Vec<_>>();
+        Ok(range
+            .clone()
+            .zip(0..)
+            .map(|(index, offset)| Difference {
+                index,
+                ours: ours.get(offset).copied(),
+                theirs: theirs.get(offset).copied(),
+            })
+            .take_while(|it| it.ours.is_some() || it.theirs.is_some())
+            .filter(|it| it.ours != it.theirs)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    lazy_static! {
+        static ref EXAMPLE_INPUT: Input = Input::from_lines([
+            "NNCB", "", "CH -> B", "HH -> N", "CB -> H", "NH -> C", "HB -> C", "HC -> B",
+            "HN -> C", "NN -> C", "BH -> H", "NC -> B", "NB -> B", "BN -> B", "BB -> N", "BC -> B",
+            "CC -> N", "CN -> C",
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn test_matches_naive_steps() {
+        let naive: String = EXAMPLE_INPUT.steps(10).into_iter().collect();
+        let expanded = EXAMPLE_INPUT.expanded(10).unwrap();
+        assert_eq!(expanded.len(), naive.len() as u64);
+        for (index, char) in naive.chars().enumerate() {
+            assert_eq!(expanded.char_at(index as u64), Ok(char));
+        }
+        assert_eq!(expanded.substring(0..3073), Ok(naive.clone()));
+        assert_eq!(
+            expanded.substring(100..250),
+            Ok(naive[100..250].to_string())
+        );
+        assert_eq!(expanded.substring(3072..3073), Ok("B".to_string()));
+        assert_eq!(expanded.substring(5..5), Ok(String::new()));
+    }
+
+    #[test]
+    fn test_huge_polymer() {
+        let expanded = EXAMPLE_INPUT.expanded(40).unwrap();
+        assert_eq!(expanded.len(), 3 * (1 << 40) + 1);
+        assert_eq!(expanded.char_at(expanded.len() - 1), Ok('B'));
+        let near_end = expanded
+            .substring((expanded.len() - 20)..expanded.len())
+            .unwrap();
+        assert_eq!(near_end.len(), 20);
+        assert_eq!(
+            expanded.char_at(expanded.len() - 20),
+            Ok(near_end.chars().next().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_huge_step_counts() {
+        // the example doubles in length every step, so it runs out of u64 indices quickly
+        assert_eq!(
+            EXAMPLE_INPUT.expanded(1_000_000_000_000_000_000).err(),
+            Some("The polymer is too long to index after 1000000000000000000 steps".to_string())
+        );
+
+        // nothing new gets inserted after the first step
+        let settles = Input::from_lines(["NNCB", "", "NN -> C"]).unwrap();
+        let expanded = settles.expanded(1_000_000_000_000_000_000).unwrap();
+        assert_eq!(
+            expanded.substring(0..expanded.len()),
+            Ok("NCNCB".to_string())
+        );
+        assert_eq!(expanded.char_at(2), Ok('N'));
+
+        // one more element every step
+        let slow = Input::from_lines(["AB", "", "AB -> A"]).unwrap();
+        let expanded = slow.expanded(1000).unwrap();
+        assert_eq!(expanded.len(), 1002);
+        assert_eq!(expanded.char_at(1000), Ok('A'));
+        assert_eq!(expanded.char_at(1001), Ok('B'));
+        assert!(slow.expanded(1_000_000_000_000_000_000).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let expanded = EXAMPLE_INPUT.expanded(1).unwrap();
+        assert_eq!(expanded.char_at(6), Ok('B'));
+        assert!(expanded.char_at(7).is_err());
+        assert!(expanded.substring(3..8).is_err());
+    }
+
+    #[test]
+    fn test_differences() {
+        let changed = Input::from_lines([
+            "NNCB", "", "CH -> B", "HH -> N", "CB -> H", "NH -> C", "HB -> C", "HC -> B",
+            "HN -> C", "NN -> C", "BH -> H", "NC -> B", "NB -> B", "BN -> B", "BB -> N", "BC -> B",
+            "CC -> N", "CN -> N",
+        ])
+        .unwrap();
+        // NNCB -> NCNBCHB -> NBCCNBBBCBHCB, and only the CN in the first step turns out differently
+        let differences = EXAMPLE_INPUT
+            .expanded(2)
+            .unwrap()
+            .differences(&changed.expanded(2).unwrap(), 0..20)
+            .unwrap();
+        assert_eq!(
+            differences,
+            vec![Difference {
+                index: 3,
+                ours: Some('C'),
+                theirs: Some('N')
+            }]
+        );
+        let differences = EXAMPLE_INPUT
+            .expanded(1)
+            .unwrap()
+            .differences(&EXAMPLE_INPUT.expanded(2).unwrap(), 5..20)
+            .unwrap();
+        assert_eq!(
+            differences.first(),
+            Some(&Difference {
+                index: 5,
+                ours: Some('H'),
+                theirs: Some('B')
+            })
+        );
+        assert_eq!(
+            differences.last(),
+            Some(&Difference {
+                index: 12,
+                ours: None,
+                theirs: Some('B')
+            })
+        );
+    }
+}
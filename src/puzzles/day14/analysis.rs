@@ -0,0 +1,254 @@
+use super::transitions::PairTransitions;
+use super::{parse_lines, CharPair, Input, InsertionRule};
+use crate::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleReport {
+    /// Pairs that can turn up in the polymer, but don't have a rule, so nothing ever gets
+    /// inserted into them
+    pub missing_rules: Vec<CharPair>,
+    /// Rules that are listed more than once
+    pub duplicate_rules: Vec<InsertionRule>,
+    /// Pairs with rules inserting different elements; the last one listed is the one that's used
+    pub conflicting_rules: Vec<(CharPair, Vec<char>)>,
+    /// Elements mentioned in the rules that can never turn up in a polymer grown from the template
+    pub unreachable_elements: Vec<char>,
+    /// Groups of pairs that can all eventually turn into each other
+    pub pair_components: Vec<Vec<CharPair>>,
+}
+
+impl RuleReport {
+    fn new(polymer: &[char], rules: &[InsertionRule]) -> Self {
+        let input = Input {
+            polymer: polymer.to_vec(),
+            insertion_rules: rules.iter().copied().collect(),
+        };
+        let transitions = PairTransitions::new(&input);
+
+        let missing_rules = transitions
+            .pairs
+            .iter()
+            .filter(|pair| !input.insertion_rules.contains_key(pair))
+            .copied()
+            .sorted()
+            .collect();
+
+        let duplicate_rules = rules.iter().duplicates().copied().sorted().collect();
+        let conflicting_rules = rules
+            .iter()
+            .into_group_map_by(|(pair, _)| *pair)
+            .into_iter()
+            .map(|(pair, rules)| {
+                (
+                    pair,
+                    rules
+                        .iter()
+                        .map(|(_, insert)| *insert)
+                        .unique()
+                        .collect_vec(),
+                )
+            })
+            .filter(|(_, inserts)| inserts.len() > 1)
+            .sorted()
+            .collect();
+
+        let reachable: HashSet<char> = polymer
+            .iter()
+            .copied()
+            .chain(transitions.pairs.iter().flat_map(|&(a, b)| [a, b]))
+            .collect();
+        let unreachable_elements = rules
+            .iter()
+            .flat_map(|&((a, b), insert)| [a, b, insert])
+            .filter(|element| !reachable.contains(element))
+            .unique()
+            .sorted()
+            .collect();
+
+        let pair_components = strongly_connected_components(&transitions.produces)
+            .into_iter()
+            .map(|component| {
+                component
+                    .into_iter()
+                    .map(|pair| transitions.pairs[pair])
+                    .sorted()
+                    .collect_vec()
+            })
+            .sorted()
+            .collect();
+
+        RuleReport {
+            missing_rules,
+            duplicate_rules,
+            conflicting_rules,
+            unreachable_elements,
+            pair_components,
+        }
+    }
+}
+
+/// Tarjan's algorithm, over a graph given as the nodes each node leads to
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Search<'a> {
+        edges: &'a [Vec<usize>],
+        next_index: usize,
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl<'a> Search<'a> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.next_index);
+            self.low_link[node] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            for &next in self.edges[node].iter() {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low_link[node] = self.low_link[node].min(self.low_link[next]);
+                    }
+                    Some(next_index) if self.on_stack[next] => {
+                        self.low_link[node] = self.low_link[node].min(next_index);
+                    }
+                    Some(_) => (),
+                }
+            }
+
+            if Some(self.low_link[node]) == self.index[node] {
+                let mut component = vec![];
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut search = Search {
+        edges,
+        next_index: 0,
+        index: vec![None; edges.len()],
+        low_link: vec![0; edges.len()],
+        stack: vec![],
+        on_stack: vec![false; edges.len()],
+        components: vec![],
+    };
+    for node in 0..edges.len() {
+        if search.index[node].is_none() {
+            search.visit(node);
+        }
+    }
+    search.components
+}
+
+pub fn analyze_lines<'a, T: IntoIterator<Item = &'a str>>(iter: T) -> Result<RuleReport, String> {
+    let (polymer, rules) = parse_lines(iter)?;
+    Ok(RuleReport::new(&polymer, &rules))
+}
+
+fn format_pair((a, b): CharPair) -> String {
+    format!("{}{}", a, b)
+}
+
+impl Input {
+    /// Like `from_lines`, but rejects rules that conflict with each other, and pairs that can
+    /// turn up in the polymer without a rule for them
+    pub fn from_lines_strict<'a, T: IntoIterator<Item = &'a str>>(iter: T) -> Result<Self, String> {
+        let (polymer, rules) = parse_lines(iter)?;
+        let report = RuleReport::new(&polymer, &rules);
+        if let Some((pair, inserts)) = report.conflicting_rules.first() {
+            return Err(format!(
+                "Conflicting rules for {}: {}",
+                format_pair(*pair),
+                inserts.iter().join(", ")
+            ));
+        }
+        if !report.missing_rules.is_empty() {
+            return Err(format!(
+                "No rules for {}",
+                report
+                    .missing_rules
+                    .iter()
+                    .map(|it| format_pair(*it))
+                    .join(", ")
+            ));
+        }
+        Ok(Input {
+            polymer,
+            insertion_rules: rules.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_LINES: [&str; 18] = [
+        "NNCB", "", "CH -> B", "HH -> N", "CB -> H", "NH -> C", "HB -> C", "HC -> B", "HN -> C",
+        "NN -> C", "BH -> H", "NC -> B", "NB -> B", "BN -> B", "BB -> N", "BC -> B", "CC -> N",
+        "CN -> C",
+    ];
+
+    #[test]
+    fn test_example_is_complete() {
+        let report = analyze_lines(EXAMPLE_LINES).unwrap();
+        assert_eq!(report.missing_rules, vec![]);
+        assert_eq!(report.duplicate_rules, vec![]);
+        assert_eq!(report.conflicting_rules, vec![]);
+        assert_eq!(report.unreachable_elements, vec![]);
+        assert_eq!(
+            report
+                .pair_components
+                .iter()
+                .map(|it| it.len())
+                .sum::<usize>(),
+            16
+        );
+        assert!(Input::from_lines_strict(EXAMPLE_LINES).is_ok());
+    }
+
+    #[test]
+    fn test_analyze() {
+        let report = analyze_lines([
+            "AB", "", "AB -> C", "AC -> A", "CB -> B", "AB -> C", "XY -> Z", "AA -> A", "AA -> C",
+        ])
+        .unwrap();
+        assert_eq!(report.missing_rules, vec![('B', 'B'), ('C', 'A')]);
+        assert_eq!(report.duplicate_rules, vec![(('A', 'B'), 'C')]);
+        assert_eq!(report.conflicting_rules, vec![(('A', 'A'), vec!['A', 'C'])]);
+        assert_eq!(report.unreachable_elements, vec!['X', 'Y', 'Z']);
+        assert_eq!(
+            report.pair_components,
+            vec![
+                vec![('A', 'A'), ('A', 'C')],
+                vec![('A', 'B')],
+                vec![('B', 'B')],
+                vec![('C', 'A')],
+                vec![('C', 'B')],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let missing_rule = Input::from_lines_strict(EXAMPLE_LINES[..17].iter().copied());
+        assert_eq!(missing_rule.err(), Some("No rules for CN".to_string()));
+        let conflict = Input::from_lines_strict(EXAMPLE_LINES.into_iter().chain(["CN -> B"]));
+        assert_eq!(
+            conflict.err(),
+            Some("Conflicting rules for CN: C, B".to_string())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use super::{CharPair, Input};
+use crate::prelude::*;
+use crate::shared::matrix::{CheckedNumber, Matrix};
+use std::collections::{HashMap, VecDeque};
+
+/// Every pair that can turn up in the polymer, numbered so that the number of each pair
+/// can be kept in a vector, and a step becomes multiplying that vector by a matrix
+pub struct PairTransitions {
+    pub pairs: Vec<CharPair>,
+    /// The pairs each pair turns into after a step.
+    /// Pairs without an insertion rule turn into themselves.
+    pub produces: Vec<Vec<usize>>,
+    pub last_element: Option<char>,
+    /// The pairs in the template, in order
+    pub initial_pairs: Vec<usize>,
+}
+
+impl PairTransitions {
+    pub fn new(input: &Input) -> Self {
+        let mut pairs: Vec<CharPair> = vec![];
+        let mut index_of = HashMap::<CharPair, usize>::new();
+        let mut queue = VecDeque::new();
+        let mut visit = |pair: CharPair, pairs: &mut Vec<CharPair>, queue: &mut VecDeque<_>| {
+            *index_of.entry(pair).or_insert_with(|| {
+                pairs.push(pair);
+                queue.push_back(pair);
+                pairs.len() - 1
+            })
+        };
+
+        let initial_pairs = input
+            .polymer
+            .windows(2)
+            .map(|window| visit((window[0], window[1]), &mut pairs, &mut queue))
+            .collect_vec();
+        let mut produces = vec![];
+        while let Some(pair) = queue.pop_front() {
+            let next = match input.insertion_rules.get(&pair) {
+                Some(&inserted) => vec![
+                    visit((pair.0, inserted), &mut pairs, &mut queue),
+                    visit((inserted, pair.1), &mut pairs, &mut queue),
+                ],
+                None => vec![visit(pair, &mut pairs, &mut queue)],
+            };
+            produces.push(next);
+        }
+
+        PairTransitions {
+            pairs,
+            produces,
+            last_element: input.polymer.last().copied(),
+            initial_pairs,
+        }
+    }
+
+    /// `matrix[to][from]` is how many of pair `to` a single pair `from` turns into
+    pub fn matrix<N: CheckedNumber>(&self) -> Matrix<N> {
+        let size = self.pairs.len();
+        let mut matrix = vec![vec![N::zero(); size]; size];
+        for (from, produced) in self.produces.iter().enumerate() {
+            for &to in produced {
+                matrix[to][from] = matrix[to][from].clone() + N::one();
+            }
+        }
+        Matrix(matrix)
+    }
+
+    fn initial_counts<N: CheckedNumber>(&self) -> Vec<N> {
+        let mut counts = vec![N::zero(); self.pairs.len()];
+        for &pair in self.initial_pairs.iter() {
+            counts[pair] = counts[pair].clone() + N::one();
+        }
+        counts
+    }
+
+    /// How many of each pair there are after some number of steps, found by squaring the matrix
+    /// so that it only takes a few dozen multiplications even for huge step counts
+    pub fn pair_counts_after<N: CheckedNumber>(&self, steps: u64) -> Result<Vec<N>, String> {
+        let overflow = || format!("Counts are too big to keep track of after {} steps", steps);
+        let mut counts = self.initial_counts();
+        let mut power = self.matrix::<N>();
+        let mut remaining = steps;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                counts = power.checked_apply(&counts).ok_or_else(overflow)?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                power = power.checked_mul(&power).ok_or_else(overflow)?;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Every element is the first in a pair, except for the very last one,
+    /// which never changes
+    pub fn element_counts<N: CheckedNumber>(
+        &self,
+        pair_counts: &[N],
+    ) -> Result<HashMap<char, N>, String> {
+        let overflow = || "Counts are too big to add up".to_string();
+        let mut result = HashMap::<char, N>::new();
+        for (&(first, _), count) in self.pairs.iter().zip(pair_counts) {
+            let total = result.entry(first).or_insert_with(N::zero);
+            *total = total.checked_add(count).ok_or_else(overflow)?;
+        }
+        if let Some(last) = self.last_element {
+            let total = result.entry(last).or_insert_with(N::zero);
+            *total = total.checked_add(&N::one()).ok_or_else(overflow)?;
+        }
+        result.retain(|_, count| !count.is_zero());
+        Ok(result)
+    }
+}
+
+impl Input {
+    pub fn element_counts_after<N: CheckedNumber>(
+        &self,
+        steps: u64,
+    ) -> Result<HashMap<char, N>, String> {
+        let transitions = PairTransitions::new(self);
+        let pair_counts = transitions.pair_counts_after(steps)?;
+        transitions.element_counts(&pair_counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use num_traits::One;
+
+    lazy_static! {
+        static ref EXAMPLE_INPUT: Input = Input::from_lines([
+            "NNCB", "", "CH -> B", "HH -> N", "CB -> H", "NH -> C", "HB -> C", "HC -> B",
+            "HN -> C", "NN -> C", "BH -> H", "NC -> B", "NB -> B", "BN -> B", "BB -> N", "BC -> B",
+            "CC -> N", "CN -> C",
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn test_matches_naive_steps() {
+        for steps in 0..=10 {
+            let expected: HashMap<char, u64> = EXAMPLE_INPUT
+                .steps(steps)
+                .into_iter()
+                .counts()
+                .into_iter()
+                .map(|(element, count)| (element, count as u64))
+                .collect();
+            let result = EXAMPLE_INPUT.element_counts_after::<u64>(steps as u64);
+            assert_eq!(result, Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_big_counts() {
+        let counts = EXAMPLE_INPUT.element_counts_after::<u128>(100).unwrap();
+        let big_counts = EXAMPLE_INPUT.element_counts_after::<BigUint>(100).unwrap();
+        for (element, count) in counts {
+            assert_eq!(big_counts[&element], BigUint::from(count));
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(EXAMPLE_INPUT.element_counts_after::<u64>(70).is_err());
+        assert!(EXAMPLE_INPUT.element_counts_after::<u128>(200).is_err());
+        let big_counts = EXAMPLE_INPUT.element_counts_after::<BigUint>(200).unwrap();
+        let length: BigUint = big_counts.values().sum();
+        // every step inserts an element between each pair, so the length almost doubles
+        assert_eq!(length, (BigUint::from(3u32) << 200) + BigUint::one());
+    }
+
+    #[test]
+    fn test_huge_step_count() {
+        // AB turns into AAB, and AA has no rule, so only one A gets added each step
+        let input = Input::from_lines(["AB", "", "AB -> A"]).unwrap();
+        let counts = input.element_counts_after::<u128>(10u64.pow(18)).unwrap();
+        assert_eq!(counts[&'A'], 10u128.pow(18) + 1);
+        assert_eq!(counts[&'B'], 1);
+    }
+}
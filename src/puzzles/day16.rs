@@ -1,68 +1,29 @@
 // Day 16: Packet Decoder
-use crate::prelude::*;
-use std::borrow::Cow;
+pub mod stream;
+
+use crate::shared::matrix::CheckedNumber;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
 use std::fmt::Debug;
 use std::str::FromStr;
-
-lazy_static! {
-    static ref PUZZLE_INPUT: Vec<u8> =
-        parse_hexadecimal(include_str!("day16_input.txt").trim()).unwrap();
-}
+use stream::{Encoding, PacketDecoder};
 
 pub fn part_one() -> Result<u32, String> {
-    let mut bit_stream = into_bit_stream(&PUZZLE_INPUT);
-    let packet = Packet::read(&mut bit_stream)?;
-    Ok(packet.version_sum())
+    puzzle_decoder().version_sum()
 }
 
 pub fn part_two() -> Result<u64, String> {
-    let mut bit_stream = into_bit_stream(&PUZZLE_INPUT);
-    let packet = Packet::read(&mut bit_stream)?;
-    packet.evaluate()
+    puzzle_decoder()
+        .evaluate_next()?
+        .ok_or_else(|| "The transmission doesn't have any packets".to_string())
 }
 
-fn parse_hexadecimal(input: &str) -> Result<Vec<u8>, String> {
-    let input = if input.len() % 2 == 0 {
-        Cow::from(input)
-    } else {
-        Cow::from(input.to_owned() + "0")
-    };
-    hex::decode(input.as_ref()).map_err(|err| err.to_string())
-}
-
-type BitStream<'a> = Box<dyn Iterator<Item = bool> + 'a>;
-
-fn into_bit_stream(bytes: &[u8]) -> BitStream {
-    Box::new(bytes.into_iter().flat_map(|&byte| {
-        (0..u8::BITS as u8).rev().map(move |bit_i| {
-            let bit = 1 << bit_i;
-            byte & bit == bit
-        })
-    }))
-}
-
-fn read_int(iter: &mut impl Iterator<Item = bool>, bits: usize) -> Result<u64, String> {
-    let mut result = 0;
-    for bit_i in (0..bits).rev() {
-        let bit_i: u64 = bit_i
-            .try_into()
-            .map_err(|_| "can't parse bit_i into an Integer")
-            .unwrap();
-        let bit = 1 << bit_i;
-
-        let bit_on = iter
-            .next()
-            .ok_or("Bit stream ran out while reading an int")?;
-
-        if bit_on {
-            result |= bit;
-        }
-    }
-    Ok(result)
+fn puzzle_decoder() -> PacketDecoder<&'static [u8]> {
+    PacketDecoder::new(include_str!("day16_input.txt").as_bytes(), Encoding::Hex)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum OperatorPacketType {
+pub enum OperatorPacketType {
     Sum,
     Product,
     Minimum,
@@ -89,149 +50,172 @@ impl OperatorPacketType {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-enum Packet {
+pub enum Packet {
     Literal(LiteralPacket),
     Operator(OperatorPacket),
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct LiteralPacket {
-    version: u8,
-    value: u64,
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LiteralPacket {
+    pub version: u8,
+    pub value: BigUint,
+}
+
+impl LiteralPacket {
+    fn evaluate_at<N: PacketValue>(&self, path: &[usize]) -> Result<N, String> {
+        N::from_literal(&self.value).ok_or_else(|| {
+            format!(
+                "Literal packet at path {:?} is too large: {}",
+                path, self.value
+            )
+        })
+    }
 }
+
 #[derive(Debug, PartialEq, Eq)]
-struct OperatorPacket {
-    version: u8,
-    packet_type: OperatorPacketType,
-    sub_packets: Vec<Packet>,
+pub struct OperatorPacket {
+    pub version: u8,
+    pub packet_type: OperatorPacketType,
+    pub sub_packets: Vec<Packet>,
 }
 
 impl OperatorPacket {
-    fn evaluate(&self) -> Result<u64, String> {
-        let evaluated_sub_packets = || {
-            self.sub_packets
-                .iter()
-                .map(|it| it.evaluate())
-                .collect::<Result<Vec<_>, _>>()
+    fn evaluate_at<N: PacketValue>(&self, path: &mut Vec<usize>) -> Result<N, String> {
+        let mut accumulator = OperatorAccumulator::new(self.packet_type);
+        for (i, sub_packet) in self.sub_packets.iter().enumerate() {
+            path.push(i);
+            let value = sub_packet.evaluate_at(path)?;
+            path.pop();
+            accumulator.push(value, path)?;
+        }
+        accumulator.finish()
+    }
+}
+
+/// Folds an operator's evaluated sub-packets in one at a time,
+/// so that evaluation never needs to hold all of them at once
+struct OperatorAccumulator<N> {
+    packet_type: OperatorPacketType,
+    value: Option<N>,
+    sub_packet_count: usize,
+}
+
+impl<N: PacketValue> OperatorAccumulator<N> {
+    fn new(packet_type: OperatorPacketType) -> Self {
+        OperatorAccumulator {
+            packet_type,
+            value: None,
+            sub_packet_count: 0,
+        }
+    }
+
+    /// `path` is the path of the operator packet itself, for error reporting
+    fn push(&mut self, sub_value: N, path: &[usize]) -> Result<(), String> {
+        let overflow_error = || {
+            format!(
+                "{:?} packet at path {:?} overflowed",
+                self.packet_type, path
+            )
         };
-        match self.packet_type {
-            OperatorPacketType::Sum => Ok(evaluated_sub_packets()?.into_iter().sum()),
-            OperatorPacketType::Product => Ok(evaluated_sub_packets()?.into_iter().product()),
-            OperatorPacketType::Minimum => evaluated_sub_packets()?
-                .into_iter()
-                .min()
-                .ok_or("Can't get the minimum of 0 sub-packets".to_string()),
-            OperatorPacketType::Maximum => evaluated_sub_packets()?
-                .into_iter()
-                .max()
-                .ok_or("Can't get the minimum of 0 sub-packets".to_string()),
-            OperatorPacketType::GreaterThan => {
-                if self.sub_packets.len() != 2 {
-                    return Err(format!(
-                        "Expected 2 subpackets for GreaterThan operation, but got {}",
-                        self.sub_packets.len()
-                    ));
-                }
-                let a = self.sub_packets[0].evaluate();
-                let b = self.sub_packets[1].evaluate();
-                if a > b {
-                    Ok(1)
-                } else {
-                    Ok(0)
-                }
-            }
-            OperatorPacketType::LessThan => {
-                if self.sub_packets.len() != 2 {
-                    return Err(format!(
-                        "Expected 2 subpackets for LessThan operation, but got {}",
-                        self.sub_packets.len()
-                    ));
-                }
-                let a = self.sub_packets[0].evaluate();
-                let b = self.sub_packets[1].evaluate();
-                if a < b {
-                    Ok(1)
-                } else {
-                    Ok(0)
+
+        let new_value = match self.value.take() {
+            None => sub_value,
+            Some(value) => match self.packet_type {
+                OperatorPacketType::Sum => {
+                    value.checked_add(&sub_value).ok_or_else(overflow_error)?
                 }
-            }
-            OperatorPacketType::EqualTo => {
-                if self.sub_packets.len() != 2 {
-                    return Err(format!(
-                        "Expected 2 subpackets for EqualTo operation, but got {}",
-                        self.sub_packets.len()
-                    ));
+                OperatorPacketType::Product => {
+                    value.checked_mul(&sub_value).ok_or_else(overflow_error)?
                 }
-                let a = self.sub_packets[0].evaluate();
-                let b = self.sub_packets[1].evaluate();
-                if a == b {
-                    Ok(1)
-                } else {
-                    Ok(0)
+                OperatorPacketType::Minimum => value.min(sub_value),
+                OperatorPacketType::Maximum => value.max(sub_value),
+                OperatorPacketType::GreaterThan
+                | OperatorPacketType::LessThan
+                | OperatorPacketType::EqualTo => {
+                    if self.sub_packet_count != 1 {
+                        return Err(format!(
+                            "Expected 2 subpackets for {:?} operation, but got at least {}",
+                            self.packet_type,
+                            self.sub_packet_count + 1
+                        ));
+                    }
+                    let result = match self.packet_type {
+                        OperatorPacketType::GreaterThan => value > sub_value,
+                        OperatorPacketType::LessThan => value < sub_value,
+                        _ => value == sub_value,
+                    };
+                    if result {
+                        N::one()
+                    } else {
+                        N::zero()
+                    }
                 }
-            }
+            },
+        };
+        self.value = Some(new_value);
+        self.sub_packet_count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<N, String> {
+        match self.packet_type {
+            OperatorPacketType::Sum => Ok(self.value.unwrap_or_else(N::zero)),
+            OperatorPacketType::Product => Ok(self.value.unwrap_or_else(N::one)),
+            OperatorPacketType::Minimum => self
+                .value
+                .ok_or("Can't get the minimum of 0 sub-packets".to_string()),
+            OperatorPacketType::Maximum => self
+                .value
+                .ok_or("Can't get the maximum of 0 sub-packets".to_string()),
+            OperatorPacketType::GreaterThan
+            | OperatorPacketType::LessThan
+            | OperatorPacketType::EqualTo => match self.value {
+                Some(value) if self.sub_packet_count == 2 => Ok(value),
+                _ => Err(format!(
+                    "Expected 2 subpackets for {:?} operation, but got {}",
+                    self.packet_type, self.sub_packet_count
+                )),
+            },
         }
     }
 }
 
-const PACKET_TYPE_LITERAL: u8 = 4;
+/// A numeric type that packets can be evaluated into
+pub trait PacketValue: CheckedNumber + Ord {
+    /// Returns None if the literal doesn't fit in this type
+    fn from_literal(value: &BigUint) -> Option<Self>;
+}
 
-impl Packet {
-    #[allow(dead_code)]
-    fn new_literal(version: u8, value: u64) -> Self {
-        Packet::Literal(LiteralPacket { version, value })
+impl PacketValue for u64 {
+    fn from_literal(value: &BigUint) -> Option<Self> {
+        value.to_u64()
     }
+}
 
-    fn read(bit_stream: &mut BitStream) -> Result<Self, String> {
-        let version: u8 = read_int(bit_stream, 3)? as u8;
-        let packet_type: u8 = read_int(bit_stream, 3)? as u8;
-
-        if packet_type == PACKET_TYPE_LITERAL {
-            let mut value_bits = vec![];
-            loop {
-                let last_group = !bit_stream
-                    .next()
-                    .ok_or("Ran out of stream while parsing literal packet")?;
-                value_bits.extend(bit_stream.take(4));
-                if last_group {
-                    break;
-                }
-            }
+impl PacketValue for u128 {
+    fn from_literal(value: &BigUint) -> Option<Self> {
+        value.to_u128()
+    }
+}
 
-            let value_bits_count = value_bits.len();
-            let value: u64 = read_int(&mut value_bits.into_iter(), value_bits_count)?;
-
-            Ok(Packet::Literal(LiteralPacket { version, value }))
-        } else {
-            let length_type_1 = bit_stream
-                .next()
-                .ok_or("Ran out of stream while parsing operator packet")?;
-
-            let sub_packets = if length_type_1 {
-                let sub_packet_count = read_int(bit_stream, 11)?;
-                (0..sub_packet_count)
-                    .map(|_| Packet::read(bit_stream))
-                    .collect::<Result<_, _>>()?
-            } else {
-                let sub_packet_length = read_int(bit_stream, 15)?;
-                let mut sub_packets_stream = bit_stream.take(sub_packet_length as usize).peekable();
-                let mut sub_packets = vec![];
-                while let Some(_) = sub_packets_stream.peek() {
-                    let mut boxed_stream: BitStream = Box::new(&mut sub_packets_stream);
-                    sub_packets.push(Packet::read(&mut boxed_stream)?);
-                }
-                sub_packets
-            };
+impl PacketValue for BigUint {
+    fn from_literal(value: &BigUint) -> Option<Self> {
+        Some(value.clone())
+    }
+}
 
-            Ok(Packet::Operator(OperatorPacket {
-                version,
-                packet_type: OperatorPacketType::from_id(packet_type)?,
-                sub_packets,
-            }))
-        }
+const PACKET_TYPE_LITERAL: u8 = 4;
+
+impl Packet {
+    #[allow(dead_code)]
+    fn new_literal(version: u8, value: u64) -> Self {
+        Packet::Literal(LiteralPacket {
+            version,
+            value: value.into(),
+        })
     }
 
-    fn version_sum(&self) -> u32 {
+    pub fn version_sum(&self) -> u32 {
         match self {
             Packet::Literal(packet) => packet.version as u32,
             Packet::Operator(packet) => {
@@ -242,10 +226,18 @@ impl Packet {
         }
     }
 
-    fn evaluate(&self) -> Result<u64, String> {
+    pub fn evaluate(&self) -> Result<u64, String> {
+        self.evaluate_as()
+    }
+
+    pub fn evaluate_as<N: PacketValue>(&self) -> Result<N, String> {
+        self.evaluate_at(&mut vec![])
+    }
+
+    fn evaluate_at<N: PacketValue>(&self, path: &mut Vec<usize>) -> Result<N, String> {
         match self {
-            Packet::Literal(packet) => Ok(packet.value),
-            Packet::Operator(packet) => packet.evaluate(),
+            Packet::Literal(packet) => packet.evaluate_at(path),
+            Packet::Operator(packet) => packet.evaluate_at(path),
         }
     }
 }
@@ -254,9 +246,9 @@ impl FromStr for Packet {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = parse_hexadecimal(s)?;
-        let mut bit_stream = into_bit_stream(&bytes);
-        Packet::read(&mut bit_stream)
+        PacketDecoder::new(s.as_bytes(), Encoding::Hex)
+            .next_packet()?
+            .ok_or_else(|| "There's no packet here".to_string())
     }
 }
 
@@ -264,63 +256,14 @@ impl FromStr for Packet {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_hex() {
-        let result = parse_hexadecimal("D2FE28");
-        assert_eq!(result, Ok(vec![0b11010010, 0b11111110, 0b00101000]));
-    }
-
-    #[test]
-    fn test_parse_hex_longer() {
-        let result = parse_hexadecimal("38006F45291200");
-        assert_eq!(
-            result,
-            Ok(vec![
-                0b00111000, 0b00000000, 0b01101111, 0b01000101, 0b00101001, 0b00010010, 0b00000000
-            ])
-        );
-    }
-
-    #[test]
-    fn test_bit_stream() {
-        let expected = "110100101111111000101000"
-            .chars()
-            .map(|it| it == '1')
-            .collect_vec();
-        let result = into_bit_stream(&vec![0b11010010, 0b11111110, 0b00101000]).collect_vec();
-        assert_eq!(result, expected);
-    }
-
-    #[test]
-    fn test_bit_stream_short() {
-        let expected = "110".chars().map(|it| it == '1').collect_vec();
-        let result = into_bit_stream(&parse_hexadecimal("D2").unwrap())
-            .take(3)
-            .collect_vec();
-        assert_eq!(result, expected);
-    }
-
-    #[test]
-    fn test_read_int() {
-        fn read_int_util<Number>(bytes: Vec<u8>, bits: usize) -> u64 {
-            let mut bit_stream = into_bit_stream(&bytes);
-            read_int(&mut bit_stream, bits).unwrap()
-        }
-        assert_eq!(read_int_util::<u8>(vec![0b11000000], 3), 6);
-        assert_eq!(read_int_util::<u8>(vec![0b10000000], 3), 4);
-        assert_eq!(read_int_util::<u16>(vec![0b01111110, 0b01010000], 12), 2021);
-    }
-
     #[test]
     fn parse_packet() {
-        let bits = into_bit_stream(&vec![0b11010010, 0b11111110, 0b00101000]).collect_vec();
-        let mut bit_stream: BitStream = Box::new(bits.iter().copied());
-        let packet = Packet::read(&mut bit_stream);
+        let packet = Packet::from_str("D2FE28");
         assert_eq!(
             packet,
             Ok(Packet::Literal(LiteralPacket {
                 version: 6,
-                value: 2021
+                value: 2021u32.into()
             }))
         );
     }
@@ -332,9 +275,7 @@ mod tests {
             packet_type: OperatorPacketType::LessThan,
             sub_packets: vec![Packet::new_literal(6, 10), Packet::new_literal(2, 20)],
         });
-        let bytes = parse_hexadecimal("38006F45291200").unwrap();
-
-        let packet = Packet::read(&mut into_bit_stream(&bytes));
+        let packet = Packet::from_str("38006F45291200");
         assert_eq!(packet, Ok(expected));
     }
 
@@ -349,9 +290,7 @@ mod tests {
                 Packet::new_literal(1, 3),
             ],
         });
-        let bytes = parse_hexadecimal("EE00D40C823060").unwrap();
-
-        let packet = Packet::read(&mut into_bit_stream(&bytes));
+        let packet = Packet::from_str("EE00D40C823060");
         assert_eq!(packet, Ok(expected));
     }
 
@@ -412,6 +351,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_evaluate_overflow() {
+        let packet = Packet::Operator(OperatorPacket {
+            version: 0,
+            packet_type: OperatorPacketType::Sum,
+            sub_packets: vec![
+                Packet::new_literal(0, 1),
+                Packet::Operator(OperatorPacket {
+                    version: 0,
+                    packet_type: OperatorPacketType::Product,
+                    sub_packets: vec![Packet::new_literal(0, u64::MAX), Packet::new_literal(0, 2)],
+                }),
+            ],
+        });
+        assert_eq!(
+            packet.evaluate(),
+            Err("Product packet at path [1] overflowed".to_string())
+        );
+        assert_eq!(packet.evaluate_as::<u128>(), Ok(u64::MAX as u128 * 2 + 1));
+        assert_eq!(
+            packet.evaluate_as::<BigUint>(),
+            Ok(BigUint::from(u64::MAX) * 2u32 + 1u32)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_large_literal() {
+        // a literal packet containing 2^64
+        let packet = Packet::from_str("123084210842108421084000").unwrap();
+        assert_eq!(
+            packet,
+            Packet::Literal(LiteralPacket {
+                version: 0,
+                value: BigUint::from(1u32) << 64
+            })
+        );
+        assert!(packet.evaluate().is_err());
+        assert_eq!(packet.evaluate_as::<u128>(), Ok(1 << 64));
+    }
+
     #[test]
     fn part_two_answer() {
         let result = part_two();
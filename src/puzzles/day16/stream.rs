@@ -0,0 +1,471 @@
+use super::{
+    LiteralPacket, OperatorAccumulator, OperatorPacket, OperatorPacketType, Packet, PacketValue,
+    PACKET_TYPE_LITERAL,
+};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::io::{BufReader, Bytes, Read};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Encoding {
+    /// ASCII hexadecimal digits, as in the puzzle input. Whitespace is ignored.
+    Hex,
+    /// The transmission's bits, packed into bytes
+    Raw,
+}
+
+/// What the decoder saw, in the order it saw it.
+/// Every `EnterOperator` is eventually followed by a matching `ExitOperator`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PacketEvent {
+    EnterOperator {
+        version: u8,
+        packet_type: OperatorPacketType,
+    },
+    Literal(LiteralPacket),
+    ExitOperator,
+}
+
+/// Decodes packets from a reader as it goes, keeping only the chain of currently open
+/// operators in memory.
+///
+/// Top-level packets are read back to back; once everything left in the
+/// transmission is 0 bits, it's treated as padding and decoding stops.
+pub struct PacketDecoder<R: Read> {
+    bits: BitReader<R>,
+    open_operators: Vec<SubPacketsLeft>,
+}
+
+enum SubPacketsLeft {
+    /// The operator ends once the bit stream reaches this position
+    UntilPosition(u64),
+    Count(u64),
+}
+
+impl<R: Read> PacketDecoder<R> {
+    pub fn new(reader: R, encoding: Encoding) -> Self {
+        PacketDecoder {
+            bits: BitReader::new(reader, encoding),
+            open_operators: vec![],
+        }
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<PacketEvent>, String> {
+        match self.open_operators.last_mut() {
+            Some(SubPacketsLeft::UntilPosition(end)) => {
+                if self.bits.position > *end {
+                    return Err(format!(
+                        "Sub-packets overran their operator's length, ending at bit {} instead of {}",
+                        self.bits.position, end
+                    ));
+                } else if self.bits.position == *end {
+                    self.open_operators.pop();
+                    return Ok(Some(PacketEvent::ExitOperator));
+                }
+            }
+            Some(SubPacketsLeft::Count(count)) => {
+                if *count == 0 {
+                    self.open_operators.pop();
+                    return Ok(Some(PacketEvent::ExitOperator));
+                }
+                *count -= 1;
+            }
+            None => {
+                if self.bits.only_padding_left()? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let version = self.bits.read_int(3)? as u8;
+        let packet_type = self.bits.read_int(3)? as u8;
+
+        if packet_type == PACKET_TYPE_LITERAL {
+            let mut value = BigUint::zero();
+            loop {
+                let last_group = !self.bits.read_bit()?;
+                value = (value << 4) | BigUint::from(self.bits.read_int(4)?);
+                if last_group {
+                    break;
+                }
+            }
+            Ok(Some(PacketEvent::Literal(LiteralPacket { version, value })))
+        } else {
+            let packet_type = OperatorPacketType::from_id(packet_type)?;
+            let sub_packets_left = if self.bits.read_bit()? {
+                SubPacketsLeft::Count(self.bits.read_int(11)?)
+            } else {
+                let sub_packet_length = self.bits.read_int(15)?;
+                SubPacketsLeft::UntilPosition(self.bits.position + sub_packet_length)
+            };
+            self.open_operators.push(sub_packets_left);
+            Ok(Some(PacketEvent::EnterOperator {
+                version,
+                packet_type,
+            }))
+        }
+    }
+
+    /// Builds the next whole top-level packet.
+    /// Only call this between top-level packets, not after consuming part of one with `next_event`.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, String> {
+        let mut open_packets: Vec<OperatorPacket> = vec![];
+        while let Some(event) = self.next_event()? {
+            let finished_packet = match event {
+                PacketEvent::EnterOperator {
+                    version,
+                    packet_type,
+                } => {
+                    open_packets.push(OperatorPacket {
+                        version,
+                        packet_type,
+                        sub_packets: vec![],
+                    });
+                    continue;
+                }
+                PacketEvent::Literal(packet) => Packet::Literal(packet),
+                PacketEvent::ExitOperator => Packet::Operator(
+                    open_packets
+                        .pop()
+                        .ok_or("Exited an operator packet that was never entered")?,
+                ),
+            };
+            match open_packets.last_mut() {
+                Some(parent) => parent.sub_packets.push(finished_packet),
+                None => return Ok(Some(finished_packet)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterates over the remaining top-level packets, stopping after the first error
+    pub fn packets(mut self) -> impl Iterator<Item = Result<Packet, String>> {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let result = self.next_packet().transpose();
+            failed = matches!(result, Some(Err(_)));
+            result
+        })
+    }
+
+    /// Sums the versions of every remaining packet, without building any of them
+    pub fn version_sum(&mut self) -> Result<u32, String> {
+        let mut sum = 0;
+        while let Some(event) = self.next_event()? {
+            match event {
+                PacketEvent::EnterOperator { version, .. } => sum += version as u32,
+                PacketEvent::Literal(packet) => sum += packet.version as u32,
+                PacketEvent::ExitOperator => {}
+            }
+        }
+        Ok(sum)
+    }
+
+    /// Evaluates the next top-level packet without building it.
+    /// Same rules as `Packet::evaluate_as`, but only one partial value is kept per open operator.
+    pub fn evaluate_next<N: PacketValue>(&mut self) -> Result<Option<N>, String> {
+        let mut open_operators: Vec<OperatorAccumulator<N>> = vec![];
+        // path of the innermost open operator
+        let mut path: Vec<usize> = vec![];
+        while let Some(event) = self.next_event()? {
+            let value = match event {
+                PacketEvent::EnterOperator { packet_type, .. } => {
+                    if let Some(parent) = open_operators.last() {
+                        path.push(parent.sub_packet_count);
+                    }
+                    open_operators.push(OperatorAccumulator::new(packet_type));
+                    continue;
+                }
+                PacketEvent::Literal(packet) => {
+                    let mut literal_path = path.clone();
+                    if let Some(parent) = open_operators.last() {
+                        literal_path.push(parent.sub_packet_count);
+                    }
+                    packet.evaluate_at(&literal_path)?
+                }
+                PacketEvent::ExitOperator => {
+                    let value = open_operators
+                        .pop()
+                        .ok_or("Exited an operator packet that was never entered")?
+                        .finish()?;
+                    if !open_operators.is_empty() {
+                        path.pop();
+                    }
+                    value
+                }
+            };
+            match open_operators.last_mut() {
+                Some(parent) => parent.push(value, &path)?,
+                None => return Ok(Some(value)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+struct BitReader<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    encoding: Encoding,
+    unit: u8,
+    unit_bits_left: u8,
+    /// Bits that were read ahead by `only_padding_left` and haven't been handed out yet
+    peeked_zeros: u64,
+    peeked_one: bool,
+    /// How many bits have been handed out so far
+    position: u64,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(reader: R, encoding: Encoding) -> Self {
+        BitReader {
+            bytes: BufReader::new(reader).bytes(),
+            encoding,
+            unit: 0,
+            unit_bits_left: 0,
+            peeked_zeros: 0,
+            peeked_one: false,
+            position: 0,
+        }
+    }
+
+    /// Reads the next hex digit or byte, along with how many bits it holds
+    fn next_unit(&mut self) -> Result<Option<(u8, u8)>, String> {
+        loop {
+            let byte = match self.bytes.next() {
+                Some(byte) => byte.map_err(|err| err.to_string())?,
+                None => return Ok(None),
+            };
+            match self.encoding {
+                Encoding::Raw => return Ok(Some((byte, u8::BITS as u8))),
+                Encoding::Hex if byte.is_ascii_whitespace() => continue,
+                Encoding::Hex => {
+                    let digit = (byte as char)
+                        .to_digit(16)
+                        .ok_or(format!("Invalid hexadecimal digit: {}", byte as char))?;
+                    return Ok(Some((digit as u8, 4)));
+                }
+            }
+        }
+    }
+
+    fn read_unpeeked_bit(&mut self) -> Result<Option<bool>, String> {
+        if self.unit_bits_left == 0 {
+            match self.next_unit()? {
+                Some((unit, bits)) => {
+                    self.unit = unit;
+                    self.unit_bits_left = bits;
+                }
+                None => return Ok(None),
+            }
+        }
+        self.unit_bits_left -= 1;
+        Ok(Some((self.unit >> self.unit_bits_left) & 1 == 1))
+    }
+
+    fn next_bit(&mut self) -> Result<Option<bool>, String> {
+        let bit = if self.peeked_zeros > 0 {
+            self.peeked_zeros -= 1;
+            Some(false)
+        } else if self.peeked_one {
+            self.peeked_one = false;
+            Some(true)
+        } else {
+            self.read_unpeeked_bit()?
+        };
+        if bit.is_some() {
+            self.position += 1;
+        }
+        Ok(bit)
+    }
+
+    fn read_bit(&mut self) -> Result<bool, String> {
+        self.next_bit()?
+            .ok_or("Bit stream ran out while reading a packet".to_string())
+    }
+
+    fn read_int(&mut self, bits: usize) -> Result<u64, String> {
+        let mut result = 0;
+        for _ in 0..bits {
+            result = (result << 1) | self.read_bit()? as u64;
+        }
+        Ok(result)
+    }
+
+    /// Checks whether the rest of the stream is all 0 bits, without consuming anything.
+    /// Peeked zeros are only counted, so a long run of padding doesn't take up memory.
+    fn only_padding_left(&mut self) -> Result<bool, String> {
+        if self.peeked_one {
+            return Ok(false);
+        }
+        loop {
+            match self.read_unpeeked_bit()? {
+                None => return Ok(true),
+                Some(false) => self.peeked_zeros += 1,
+                Some(true) => {
+                    self.peeked_one = true;
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn hex_decoder(input: &str) -> PacketDecoder<&[u8]> {
+        PacketDecoder::new(input.as_bytes(), Encoding::Hex)
+    }
+
+    #[test]
+    fn test_read_int() {
+        let mut bits = BitReader::new(&[0b11000000, 0b01111110, 0b01010000][..], Encoding::Raw);
+        assert_eq!(bits.read_int(3), Ok(6));
+        assert_eq!(bits.read_int(5), Ok(0));
+        assert_eq!(bits.read_int(12), Ok(2021));
+        assert_eq!(bits.position, 20);
+        let mut bits = BitReader::new("D 2".as_bytes(), Encoding::Hex);
+        assert_eq!(bits.read_int(3), Ok(6));
+        assert_eq!(bits.read_int(5), Ok(18));
+        assert!(bits.read_int(1).is_err());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut decoder = hex_decoder("38006F45291200");
+        let mut events = vec![];
+        while let Some(event) = decoder.next_event().unwrap() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                PacketEvent::EnterOperator {
+                    version: 1,
+                    packet_type: OperatorPacketType::LessThan
+                },
+                PacketEvent::Literal(LiteralPacket {
+                    version: 6,
+                    value: 10u32.into()
+                }),
+                PacketEvent::Literal(LiteralPacket {
+                    version: 2,
+                    value: 20u32.into()
+                }),
+                PacketEvent::ExitOperator,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_packet_matches_tree() {
+        for input in [
+            "D2FE28",
+            "38006F45291200",
+            "EE00D40C823060",
+            "8A004A801A8002F478",
+            "620080001611562C8802118E34",
+            "C0015000016115A2E0802F182340",
+            "A0016C880162017C3686B18A3D4780",
+        ] {
+            let packets = hex_decoder(input)
+                .packets()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(packets, vec![Packet::from_str(input).unwrap()]);
+        }
+    }
+
+    #[test]
+    fn test_raw_encoding() {
+        let mut decoder =
+            PacketDecoder::new(&[0b11010010, 0b11111110, 0b00101000][..], Encoding::Raw);
+        assert_eq!(
+            decoder.next_packet(),
+            Ok(Some(Packet::new_literal(6, 2021)))
+        );
+        assert_eq!(decoder.next_packet(), Ok(None));
+    }
+
+    #[test]
+    fn test_multiple_top_level_packets() {
+        // two copies of D2FE28's literal packet, back to back
+        let packets = hex_decoder("D2FE2E97F14000\n")
+            .packets()
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            packets,
+            Ok(vec![
+                Packet::new_literal(6, 2021),
+                Packet::new_literal(6, 2021)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        assert!(hex_decoder("38006F4529").next_packet().is_err());
+    }
+
+    #[test]
+    fn test_streaming_version_sum() {
+        assert_eq!(hex_decoder("8A004A801A8002F478").version_sum(), Ok(16));
+        assert_eq!(
+            hex_decoder("620080001611562C8802118E34").version_sum(),
+            Ok(12)
+        );
+        assert_eq!(
+            hex_decoder("C0015000016115A2E0802F182340").version_sum(),
+            Ok(23)
+        );
+        assert_eq!(
+            hex_decoder("A0016C880162017C3686B18A3D4780").version_sum(),
+            Ok(31)
+        );
+    }
+
+    #[test]
+    fn test_streaming_evaluate() {
+        for (input, expected) in [
+            ("C200B40A82", 3),
+            ("04005AC33890", 54),
+            ("880086C3E88112", 7),
+            ("CE00C43D881120", 9),
+            ("D8005AC2A8F0", 1),
+            ("F600BC2D8F", 0),
+            ("9C005AC2F8F0", 0),
+            ("9C0141080250320F1802104A08", 1),
+        ] {
+            assert_eq!(
+                hex_decoder(input).evaluate_next::<u64>(),
+                Ok(Some(expected)),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_streaming_evaluate_overflow() {
+        // Sum(1, Product(u64::MAX, 2))
+        let input = "02008408300427FFFFFFFFFFFFFFFFFF7882";
+        let expected_error = Err("Product packet at path [1] overflowed".to_string());
+        assert_eq!(
+            Packet::from_str(input).unwrap().evaluate(),
+            expected_error.clone()
+        );
+        assert_eq!(
+            hex_decoder(input).evaluate_next::<u64>(),
+            expected_error.map(Some)
+        );
+        assert_eq!(
+            hex_decoder(input).evaluate_next::<u128>(),
+            Ok(Some(u64::MAX as u128 * 2 + 1))
+        );
+    }
+}
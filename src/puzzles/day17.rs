@@ -1,9 +1,13 @@
 // Day 17: Trick Shot
 use crate::prelude::*;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use regex::Regex;
+use std::collections::HashMap;
 use std::ops::{Add, AddAssign, RangeInclusive};
 
+pub mod plot;
+pub mod targets;
+
 lazy_static! {
     static ref PUZZLE_INPUT: BoxArea2D =
         parse_target_area(include_str!("day17_input.txt").trim()).unwrap();
@@ -14,23 +18,23 @@ lazy_static! {
 
 type Int = i32;
 
-pub fn part_one() -> Option<Int> {
-    let result = find_highest_trajectory(&PUZZLE_INPUT);
-    result.map(|it| it.highest_y)
+pub fn part_one() -> Result<Option<Int>> {
+    let result = find_highest_trajectory(&PUZZLE_INPUT)?;
+    Ok(result.map(|it| it.highest_y))
 }
 
-pub fn part_two() -> usize {
-    find_all_possible_trajectories(&PUZZLE_INPUT).len()
+pub fn part_two() -> Result<usize> {
+    Ok(find_all_possible_trajectories(&PUZZLE_INPUT)?.len())
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct Vec2 {
-    x: Int,
-    y: Int,
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Vec2 {
+    pub x: Int,
+    pub y: Int,
 }
 
 impl Vec2 {
-    fn new(x: Int, y: Int) -> Self {
+    pub fn new(x: Int, y: Int) -> Self {
         Vec2 { x, y }
     }
 }
@@ -49,35 +53,68 @@ impl AddAssign for Vec2 {
     }
 }
 
+/// How the probe moves after launch.
+/// Drag slows the x velocity toward zero by up to `drag` each step, so it should never be negative.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Physics {
+    pub gravity: Int,
+    pub drag: Int,
+    pub origin: Vec2,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            gravity: 1,
+            drag: 1,
+            origin: Vec2::new(0, 0),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct Probe {
+pub struct Probe {
     position: Vec2,
     velocity: Vec2,
+    physics: Physics,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum LaunchResult {
+pub enum LaunchResult {
     Hit { highest_y: Int },
     Missed,
 }
 
 impl Probe {
-    fn new(velocity: Vec2) -> Self {
+    pub fn new(velocity: Vec2) -> Self {
+        Probe::with_physics(velocity, Physics::default())
+    }
+
+    pub fn with_physics(velocity: Vec2, physics: Physics) -> Self {
         Probe {
-            position: Vec2::new(0, 0),
+            position: physics.origin,
             velocity,
+            physics,
         }
     }
 
-    fn step(&mut self) {
+    pub fn step(&mut self) {
         self.position += self.velocity;
         let x_sign = self.velocity.x.signum();
-        self.velocity.x -= x_sign;
-        self.velocity.y -= 1;
+        self.velocity.x -= x_sign * self.physics.drag.min(self.velocity.x.abs());
+        self.velocity.y -= self.physics.gravity;
     }
 
+    /// Whether the probe is heading away from the target on either axis with nothing to turn it around
     fn missed_target(&self, target: &BoxArea2D) -> bool {
-        self.velocity.y < 0 && self.position.y < target.bottom_left.y
+        let Vec2 { x, y } = self.position;
+        let gravity = self.physics.gravity;
+        let falling_away = y < target.bottom_left.y && self.velocity.y <= 0 && gravity >= 0;
+        let rising_away = y > target.top_right.y && self.velocity.y >= 0 && gravity <= 0;
+        // drag never speeds the probe up or turns it around
+        let drifting_away = (x < target.bottom_left.x && self.velocity.x <= 0)
+            || (x > target.top_right.x && self.velocity.x >= 0);
+        falling_away || rising_away || drifting_away
     }
 
     #[cfg(test)]
@@ -88,7 +125,16 @@ impl Probe {
         };
     }
 
-    fn launch(&mut self, target: &BoxArea2D) -> LaunchResult {
+    pub fn launch(&mut self, target: &BoxArea2D) -> LaunchResult {
+        self.launch_observed(target, |_| {})
+    }
+
+    /// Like `launch`, but calls `observe` with every position the probe moves to
+    fn launch_observed(
+        &mut self,
+        target: &BoxArea2D,
+        mut observe: impl FnMut(Vec2),
+    ) -> LaunchResult {
         let mut highest_y = self.position.y;
         loop {
             if target.contains(self.position) {
@@ -96,6 +142,7 @@ impl Probe {
             }
 
             self.step();
+            observe(self.position);
 
             if self.missed_target(target) {
                 return LaunchResult::Missed;
@@ -109,9 +156,9 @@ impl Probe {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct BoxArea2D {
-    bottom_left: Vec2,
-    top_right: Vec2,
+pub struct BoxArea2D {
+    pub bottom_left: Vec2,
+    pub top_right: Vec2,
 }
 
 impl BoxArea2D {
@@ -123,12 +170,12 @@ impl BoxArea2D {
         RangeInclusive::new(self.bottom_left.y, self.top_right.y)
     }
 
-    fn contains(&self, point: Vec2) -> bool {
+    pub fn contains(&self, point: Vec2) -> bool {
         self.x_range().contains(&point.x) && self.y_range().contains(&point.y)
     }
 }
 
-fn parse_target_area(input: &str) -> Result<BoxArea2D> {
+pub fn parse_target_area(input: &str) -> Result<BoxArea2D> {
     let re_captures = INPUT_REGEX
         .captures(input)
         .ok_or(anyhow!("input '{}' does not match expected format", input))?;
@@ -166,50 +213,133 @@ struct HighestTrajectoryResult {
     starting_velocity: Vec2,
 }
 
-fn find_all_possible_trajectories(target: &BoxArea2D) -> Vec<HighestTrajectoryResult> {
-    let furthest_x = [target.bottom_left.x, target.top_right.x]
+fn find_all_possible_trajectories(target: &BoxArea2D) -> Result<Vec<HighestTrajectoryResult>> {
+    let mut velocities = find_all_hitting_velocities(target)?
+        .into_iter()
+        .collect_vec();
+    velocities.sort_by_key(|(velocity, _)| (velocity.y, velocity.x));
+
+    Ok(velocities
+        .into_iter()
+        .map(|(velocity, hit_step)| HighestTrajectoryResult {
+            highest_y: peak_height(velocity.y, hit_step),
+            starting_velocity: velocity,
+        })
+        .collect())
+}
+
+fn find_highest_trajectory(target: &BoxArea2D) -> Result<Option<HighestTrajectoryResult>> {
+    let all_trajectories = find_all_possible_trajectories(target)?;
+    Ok(all_trajectories
         .into_iter()
-        .max_by_key(|x| x.abs())
-        .unwrap();
-    let possible_x_values = if furthest_x > 0 {
-        RangeInclusive::new(0, furthest_x)
+        .max_by_key(|result| result.highest_y))
+}
+
+fn triangular(n: Int) -> Int {
+    n * (n + 1) / 2
+}
+
+/// How high a probe launched with this y velocity rises under the default physics,
+/// up until the step where it hits the target. It climbs until gravity has used up its velocity,
+/// unless it hits a target above the launcher on the way up.
+fn peak_height(y_velocity: Int, hit_step: Int) -> Int {
+    let climbing_steps = y_velocity.clamp(0, hit_step);
+    climbing_steps * y_velocity - triangular(climbing_steps - 1)
+}
+
+fn div_ceil(numerator: Int, denominator: Int) -> Int {
+    -(-numerator).div_euclid(denominator)
+}
+
+/// Works out every launch velocity that hits the target under the default physics,
+/// along with the first step that it's inside the target, without simulating anything.
+///
+/// After `n` steps, a probe launched with y velocity `vy` is at `n * vy - triangular(n - 1)`,
+/// so for each step count, the y velocities that are inside the target at that moment
+/// form a range. The same goes for x velocities, except that drag eventually stops the probe
+/// at `triangular(vx)`. Any pair of x and y velocities that are both in range at the same step hits.
+fn find_all_hitting_velocities(target: &BoxArea2D) -> Result<HashMap<Vec2, Int>> {
+    let (x_min, x_max) = (target.bottom_left.x, target.top_right.x);
+    let (y_min, y_max) = (target.bottom_left.y, target.top_right.y);
+
+    if target.contains(Vec2::new(0, 0)) {
+        bail!("The target contains the launcher, so every velocity hits it");
+    }
+
+    // x speeds where drag brings the probe to a stop inside the target, on either side of the launcher
+    let stopping_speeds = |min: Int, max: Int| -> Vec<Int> {
+        (0..)
+            .take_while(|&speed| triangular(speed) <= max)
+            .filter(|&speed| triangular(speed) >= min)
+            .collect()
+    };
+    let stopping_speeds_right = stopping_speeds(x_min, x_max);
+    let stopping_speeds_left = stopping_speeds(-x_max, -x_min);
+
+    // Going up, the probe only visits heights it'll pass through again on the way back down,
+    // where it returns to the launcher's height at step 2 * vy + 1 and then falls by vy + 1.
+    let max_y_velocity = if y_max < 0 {
+        -y_min - 1
+    } else if y_min > 0 {
+        y_max
     } else {
-        RangeInclusive::new(furthest_x, 0)
+        if !stopping_speeds_right.is_empty() || !stopping_speeds_left.is_empty() {
+            bail!(
+                "The probe can stop inside the target while passing through the launcher's height \
+                at any step, so infinitely many velocities hit it"
+            );
+        }
+        // Without stopping, the probe can only be inside the target for as long as it's moving
+        let longest_x_flight = x_min.abs().max(x_max.abs());
+        y_max.max(-y_min - 1).max(0) + longest_x_flight
     };
+    let max_steps = 2 * max_y_velocity + 1 + y_min.abs();
+
+    let mut velocities = HashMap::new();
+    for steps in 1..=max_steps {
+        let fallen = triangular(steps - 1);
+        let y_velocities = div_ceil(y_min + fallen, steps)..=(y_max + fallen).div_euclid(steps);
+        if y_velocities.is_empty() {
+            continue;
+        }
 
-    let possible_y_values = RangeInclusive::new(-250, 250);
+        let x_velocities = x_speeds_after_steps(x_min, x_max, steps, &stopping_speeds_right).chain(
+            x_speeds_after_steps(-x_max, -x_min, steps, &stopping_speeds_left)
+                .filter(|&speed| speed > 0)
+                .map(|speed| -speed),
+        );
 
-    possible_y_values
-        .into_par_iter()
-        .flat_map(|y_vel| {
-            possible_x_values
-                .clone()
-                .into_par_iter()
-                .map(move |x_vel| (x_vel, y_vel))
-        })
-        .filter_map(|(x_vel, y_vel)| {
-            let velocity = Vec2::new(x_vel, y_vel);
-            match Probe::new(velocity).launch(target) {
-                LaunchResult::Hit { highest_y } => Some(HighestTrajectoryResult {
-                    highest_y,
-                    starting_velocity: velocity,
-                }),
-                LaunchResult::Missed => None,
+        for x in x_velocities {
+            for y in y_velocities.clone() {
+                velocities.entry(Vec2::new(x, y)).or_insert(steps);
             }
-        })
-        .collect()
+        }
+    }
+    Ok(velocities)
 }
 
-fn find_highest_trajectory(target: &BoxArea2D) -> Option<HighestTrajectoryResult> {
-    let all_trajectories = find_all_possible_trajectories(target);
-    all_trajectories
-        .into_iter()
-        .max_by_key(|result| result.highest_y)
+/// Non-negative x speeds that leave the probe between `min` and `max` after exactly `steps` steps
+fn x_speeds_after_steps<'a>(
+    min: Int,
+    max: Int,
+    steps: Int,
+    stopping_speeds: &'a [Int],
+) -> impl Iterator<Item = Int> + 'a {
+    // still moving: x = steps * speed - triangular(steps - 1)
+    let fallen = triangular(steps - 1);
+    let moving = div_ceil(min + fallen, steps).max(steps)..=(max + fallen).div_euclid(steps);
+    // already stopped: x = triangular(speed)
+    let stopped = stopping_speeds
+        .iter()
+        .copied()
+        .take_while(move |&speed| speed < steps);
+    moving.chain(stopped)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     lazy_static! {
         static ref EXAMPLE_INPUT: BoxArea2D =
@@ -240,6 +370,54 @@ mod tests {
         assert!(!Probe::new(Vec2::new(17, -4)).launch_hits_target(&EXAMPLE_INPUT));
     }
 
+    #[test]
+    fn test_custom_physics() {
+        let mut probe = Probe::with_physics(
+            Vec2::new(5, 3),
+            Physics {
+                gravity: 2,
+                drag: 2,
+                origin: Vec2::new(-1, 1),
+            },
+        );
+        let mut positions = vec![];
+        for _ in 0..4 {
+            probe.step();
+            positions.push(probe.position);
+        }
+        assert_eq!(
+            positions,
+            vec![
+                Vec2::new(4, 4),
+                Vec2::new(7, 5),
+                Vec2::new(8, 4),
+                Vec2::new(8, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_target_above_with_upward_gravity() {
+        let physics = Physics {
+            gravity: -1,
+            drag: 0,
+            origin: Vec2::new(0, 0),
+        };
+        let target = parse_target_area("target area: x=5..6, y=10..12").unwrap();
+        assert_eq!(
+            Probe::with_physics(Vec2::new(1, 0), physics).launch(&target),
+            LaunchResult::Hit { highest_y: 10 }
+        );
+        assert_eq!(
+            Probe::with_physics(Vec2::new(1, 5), physics).launch(&target),
+            LaunchResult::Missed
+        );
+        assert_eq!(
+            Probe::with_physics(Vec2::new(0, 0), physics).launch(&target),
+            LaunchResult::Missed
+        );
+    }
+
     #[test]
     fn test_highest_y() {
         assert_eq!(
@@ -248,10 +426,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_peak_height_matches_launch() {
+        for input in [
+            "target area: x=20..30, y=-10..-5",
+            // above the launcher, where some probes hit on the way up
+            "target area: x=20..30, y=5..10",
+            "target area: x=-12..-4, y=3..7",
+        ] {
+            let target = parse_target_area(input).unwrap();
+            for (velocity, hit_step) in find_all_hitting_velocities(&target).unwrap() {
+                assert_eq!(
+                    Probe::new(velocity).launch(&target),
+                    LaunchResult::Hit {
+                        highest_y: peak_height(velocity.y, hit_step)
+                    },
+                    "{:?} at {}",
+                    velocity,
+                    input
+                );
+            }
+        }
+        // straight into the target on the first step, before climbing any higher
+        let above = parse_target_area("target area: x=20..30, y=5..10").unwrap();
+        let velocity = Vec2::new(20, 10);
+        assert_eq!(
+            find_all_hitting_velocities(&above).unwrap().get(&velocity),
+            Some(&1)
+        );
+        assert_eq!(
+            Probe::new(velocity).launch(&above),
+            LaunchResult::Hit { highest_y: 10 }
+        );
+    }
+
     #[test]
     fn test_highest_trajectory() {
         assert_eq!(
-            find_highest_trajectory(&EXAMPLE_INPUT),
+            find_highest_trajectory(&EXAMPLE_INPUT).unwrap(),
             Some(HighestTrajectoryResult {
                 highest_y: 45,
                 // I'd have expected a value of 6,9, but 7,9 also works and the requirements
@@ -263,19 +475,69 @@ mod tests {
 
     #[test]
     fn part_one_answer() {
-        let result = part_one().unwrap();
+        let result = part_one().unwrap().unwrap();
         assert!(result > 2211);
         assert_eq!(result, 9180);
     }
 
     #[test]
     fn get_all_possible_trajectories() {
-        assert_eq!(find_all_possible_trajectories(&EXAMPLE_INPUT).len(), 112);
+        assert_eq!(
+            find_all_possible_trajectories(&EXAMPLE_INPUT)
+                .unwrap()
+                .len(),
+            112
+        );
+    }
+
+    /// Brute-force simulation over a window of velocities that's known to be big enough
+    fn simulate_all_hitting_velocities(target: &BoxArea2D, window: Int) -> HashSet<Vec2> {
+        (-window..=window)
+            .flat_map(|x| (-window..=window).map(move |y| Vec2::new(x, y)))
+            .filter(|&velocity| Probe::new(velocity).launch_hits_target(target))
+            .collect()
+    }
+
+    #[test]
+    fn test_hitting_velocities_match_simulation() {
+        for input in [
+            "target area: x=20..30, y=-10..-5",
+            // left of the launcher
+            "target area: x=-30..-20, y=-10..-5",
+            // above the launcher
+            "target area: x=20..30, y=5..10",
+            "target area: x=-12..-4, y=3..7",
+            // directly below the launcher
+            "target area: x=-3..3, y=-10..-5",
+            // level with the launcher, but too far out for the probe to stop inside it
+            "target area: x=7..9, y=-2..2",
+        ] {
+            let target = parse_target_area(input).unwrap();
+            assert_eq!(
+                find_all_hitting_velocities(&target)
+                    .unwrap()
+                    .into_keys()
+                    .collect::<HashSet<_>>(),
+                simulate_all_hitting_velocities(&target, 60),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_infinite_hitting_velocities() {
+        let contains_launcher = parse_target_area("target area: x=-5..5, y=-5..5").unwrap();
+        assert!(find_all_hitting_velocities(&contains_launcher).is_err());
+
+        // any probe with an x velocity of 3 stops at x=6, and every upward shot comes back to y=0
+        let level_with_launcher = parse_target_area("target area: x=6..7, y=-2..2").unwrap();
+        assert!(find_all_hitting_velocities(&level_with_launcher).is_err());
     }
 
     #[test]
     fn part_two_answer() {
-        let result = part_two();
+        let result = part_two().unwrap();
         assert_eq!(result, 3767);
     }
 }
@@ -0,0 +1,183 @@
+use super::{BoxArea2D, Int, LaunchResult, Probe, Vec2};
+use crate::prelude::*;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Every position a probe moved through on its way to hitting or missing the target
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RecordedLaunch {
+    pub origin: Vec2,
+    pub positions: Vec<Vec2>,
+    pub target: BoxArea2D,
+    pub result: LaunchResult,
+}
+
+impl Probe {
+    pub fn launch_recorded(&mut self, target: &BoxArea2D) -> RecordedLaunch {
+        let origin = self.position;
+        let mut positions = vec![];
+        let result = self.launch_observed(target, |position| positions.push(position));
+        RecordedLaunch {
+            origin,
+            positions,
+            target: *target,
+            result,
+        }
+    }
+}
+
+impl RecordedLaunch {
+    /// The smallest box that fits the launcher, the target and the whole trajectory
+    fn bounds(&self) -> BoxArea2D {
+        let points = [self.origin, self.target.bottom_left, self.target.top_right]
+            .into_iter()
+            .chain(self.positions.iter().copied());
+        let (x_min, x_max) = points
+            .clone()
+            .map(|it| it.x)
+            .minmax()
+            .into_option()
+            .unwrap();
+        let (y_min, y_max) = points.map(|it| it.y).minmax().into_option().unwrap();
+        BoxArea2D {
+            bottom_left: Vec2::new(x_min, y_min),
+            top_right: Vec2::new(x_max, y_max),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let bounds = self.bounds();
+        // SVG's y axis points down, so every y coordinate is flipped
+        let view_box = format!(
+            "{} {} {} {}",
+            bounds.bottom_left.x - 1,
+            -bounds.top_right.y - 1,
+            bounds.top_right.x - bounds.bottom_left.x + 2,
+            bounds.top_right.y - bounds.bottom_left.y + 2
+        );
+        let target = format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="lightblue" />"#,
+            self.target.bottom_left.x as f32 - 0.5,
+            -self.target.top_right.y as f32 - 0.5,
+            self.target.top_right.x - self.target.bottom_left.x + 1,
+            self.target.top_right.y - self.target.bottom_left.y + 1
+        );
+        let path = std::iter::once(self.origin)
+            .chain(self.positions.iter().copied())
+            .map(|point| format!("{},{}", point.x, -point.y))
+            .join(" ");
+        let trajectory = format!(
+            r#"<polyline points="{}" fill="none" stroke="black" stroke-width="0.2" />"#,
+            path
+        );
+        let launcher = format!(
+            r#"<circle cx="{}" cy="{}" r="0.5" fill="green" />"#,
+            self.origin.x, -self.origin.y
+        );
+        let positions = self
+            .positions
+            .iter()
+            .map(|point| {
+                format!(
+                    r#"<circle cx="{}" cy="{}" r="0.3" fill="black" />"#,
+                    point.x, -point.y
+                )
+            })
+            .join("\n  ");
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{}\">\n  {}\n  {}\n  {}\n  {}\n</svg>\n",
+            view_box, target, trajectory, launcher, positions
+        )
+    }
+}
+
+/// Draws the launch the way the puzzle does:
+/// `S` for the launcher, `#` for the probe and `T` for the target area
+impl Display for RecordedLaunch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bounds = self.bounds();
+        let positions: HashSet<Vec2> = self.positions.iter().copied().collect();
+        let display = (bounds.bottom_left.y..=bounds.top_right.y)
+            .rev()
+            .map(|y: Int| {
+                bounds
+                    .x_range()
+                    .map(|x| {
+                        let point = Vec2::new(x, y);
+                        if point == self.origin {
+                            "S"
+                        } else if positions.contains(&point) {
+                            "#"
+                        } else if self.target.contains(point) {
+                            "T"
+                        } else {
+                            "."
+                        }
+                    })
+                    .join("")
+            })
+            .join("\n");
+
+        f.write_str(display.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_target_area;
+    use super::*;
+
+    lazy_static! {
+        static ref EXAMPLE_INPUT: BoxArea2D =
+            parse_target_area("target area: x=20..30, y=-10..-5").unwrap();
+    }
+
+    #[test]
+    fn test_plot() {
+        let launch = Probe::new(Vec2::new(7, 2)).launch_recorded(&EXAMPLE_INPUT);
+        assert_eq!(launch.result, LaunchResult::Hit { highest_y: 3 });
+        let expected = [
+            ".............#....#............",
+            ".......#..............#........",
+            "...............................",
+            "S........................#.....",
+            "...............................",
+            "...............................",
+            "...........................#...",
+            "...............................",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTT#TT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+        ]
+        .join("\n");
+        assert_eq!(launch.to_string(), expected);
+    }
+
+    #[test]
+    fn test_plot_missed() {
+        let launch = Probe::new(Vec2::new(17, -4)).launch_recorded(&EXAMPLE_INPUT);
+        assert_eq!(launch.result, LaunchResult::Missed);
+        assert_eq!(launch.positions, vec![Vec2::new(17, -4), Vec2::new(33, -9)]);
+        let rendered = launch.to_string();
+        assert_eq!(rendered.lines().count(), 11);
+        assert!(rendered.lines().all(|line| line.len() == 34));
+        assert!(rendered.ends_with("TTTTTTTTTTT..."));
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = Probe::new(Vec2::new(7, 2))
+            .launch_recorded(&EXAMPLE_INPUT)
+            .to_svg();
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -4 32 15">"#)
+        );
+        assert!(svg.contains(r#"<rect x="19.5" y="4.5" width="11" height="6" fill="lightblue" />"#));
+        assert!(svg.contains(r#"points="0,0 7,-2 13,-3 18,-3 22,-2 25,0 27,3 28,7""#));
+        assert_eq!(svg.matches("<circle").count(), 8);
+    }
+}
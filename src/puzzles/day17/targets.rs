@@ -0,0 +1,116 @@
+use super::{BoxArea2D, Physics, Probe, Vec2};
+use crate::prelude::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TargetHit {
+    /// Index into the list of targets the probe was launched at
+    pub target: usize,
+    /// How many steps after launch the probe first entered the target
+    pub step: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VelocityHits {
+    pub velocity: Vec2,
+    pub hits: Vec<TargetHit>,
+}
+
+impl Probe {
+    /// Keeps the probe flying until every target has either been hit or missed
+    pub fn launch_at_targets(&mut self, targets: &[BoxArea2D]) -> Vec<TargetHit> {
+        let mut hit_steps: Vec<Option<usize>> = vec![None; targets.len()];
+        let mut missed = vec![false; targets.len()];
+        let mut step = 0;
+        loop {
+            for (i, target) in targets.iter().enumerate() {
+                if hit_steps[i].is_some() || missed[i] {
+                    continue;
+                }
+                if target.contains(self.position) {
+                    hit_steps[i] = Some(step);
+                } else if self.missed_target(target) {
+                    missed[i] = true;
+                }
+            }
+
+            if hit_steps
+                .iter()
+                .zip(missed.iter())
+                .all(|(hit, missed)| hit.is_some() || *missed)
+            {
+                break;
+            }
+
+            self.step();
+            step += 1;
+        }
+
+        hit_steps
+            .into_iter()
+            .enumerate()
+            .filter_map(|(target, step)| step.map(|step| TargetHit { target, step }))
+            .collect()
+    }
+}
+
+/// Launches a probe at every velocity, keeping the ones that hit at least one of the targets
+pub fn find_target_hits(
+    targets: &[BoxArea2D],
+    physics: Physics,
+    velocities: impl IntoParallelIterator<Item = Vec2>,
+) -> Vec<VelocityHits> {
+    velocities
+        .into_par_iter()
+        .filter_map(|velocity| {
+            let hits = Probe::with_physics(velocity, physics).launch_at_targets(targets);
+            if hits.is_empty() {
+                None
+            } else {
+                Some(VelocityHits { velocity, hits })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_target_area;
+    use super::*;
+
+    #[test]
+    fn test_launch_at_targets() {
+        let targets = [
+            parse_target_area("target area: x=20..30, y=-10..-5").unwrap(),
+            // the probe passes through (18,3) on its way to the first target
+            parse_target_area("target area: x=17..19, y=3..3").unwrap(),
+            parse_target_area("target area: x=-10..-5, y=-10..-5").unwrap(),
+        ];
+        assert_eq!(
+            Probe::new(Vec2::new(7, 2)).launch_at_targets(&targets),
+            vec![
+                TargetHit { target: 0, step: 7 },
+                TargetHit { target: 1, step: 3 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_target_hits() {
+        let targets = [
+            parse_target_area("target area: x=20..30, y=-10..-5").unwrap(),
+            parse_target_area("target area: x=-30..-20, y=-10..-5").unwrap(),
+        ];
+        let velocities = (-30..=30)
+            .flat_map(|x| (-10..=10).map(move |y| Vec2::new(x, y)))
+            .collect_vec();
+        let result = find_target_hits(&targets, Physics::default(), velocities);
+
+        // the targets mirror each other, so each one gets the example's 112 velocities
+        assert_eq!(result.len(), 224);
+        assert!(result.iter().all(|it| it.hits.len() == 1));
+        assert!(result.contains(&VelocityHits {
+            velocity: Vec2::new(-7, 2),
+            hits: vec![TargetHit { target: 1, step: 7 }],
+        }));
+    }
+}
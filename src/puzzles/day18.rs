@@ -1,8 +1,14 @@
 // Day 18: Snailfish
+mod flat;
+pub mod trace;
+
 use crate::prelude::*;
-use anyhow::{anyhow, bail, Error, Result};
+use anyhow::{bail, Error, Result};
+use flat::FlatSnailfishNumber;
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::{fmt::Display, iter::Peekable, ops::Add, str::FromStr, sync::Arc};
 
 lazy_static! {
@@ -18,10 +24,57 @@ pub fn part_one() -> u32 {
 }
 
 pub fn part_two() -> u32 {
-    find_largest_combination(&PUZZLE_INPUT).unwrap()
+    let numbers: Box<[FlatSnailfishNumber]> =
+        PUZZLE_INPUT.iter().map(FlatSnailfishNumber::from).collect();
+    find_largest_combination(&numbers).unwrap().magnitude
+}
+
+type Digit = u32;
+
+/// The parameters of snailfish arithmetic; `default()` gives the puzzle's rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnailfishRules {
+    /// Pairs nested inside this many pairs explode
+    explode_depth: usize,
+    /// Regular numbers this large or larger split
+    split_threshold: Digit,
+    /// Multipliers for the left and right halves of a pair's magnitude
+    magnitude_weights: (u32, u32),
+}
+
+impl SnailfishRules {
+    pub fn new(
+        explode_depth: usize,
+        split_threshold: Digit,
+        magnitude_weights: (u32, u32),
+    ) -> Result<Self> {
+        if explode_depth == 0 {
+            bail!("The outermost pair can't explode, so explode_depth has to be at least 1");
+        }
+        if split_threshold < 2 {
+            // splitting 1 or 0 makes a pair that has another 1 or 0 in it, forever
+            bail!(
+                "Numbers below 2 can't split, so split_threshold has to be at least 2, not {}",
+                split_threshold
+            );
+        }
+        Ok(SnailfishRules {
+            explode_depth,
+            split_threshold,
+            magnitude_weights,
+        })
+    }
 }
 
-type Digit = u8;
+impl Default for SnailfishRules {
+    fn default() -> Self {
+        SnailfishRules {
+            explode_depth: 4,
+            split_threshold: 10,
+            magnitude_weights: (3, 2),
+        }
+    }
+}
 
 macro_rules! element_literal {
     ([$left:tt, $right:tt]) => {
@@ -39,7 +92,7 @@ macro_rules! snailfish_num {
 }
 
 #[derive(Clone, PartialEq, Eq)]
-struct SnailfishNumber(Arc<(Element, Element)>);
+pub struct SnailfishNumber(Arc<(Element, Element)>);
 
 impl SnailfishNumber {
     fn new(left: Element, right: Element) -> SnailfishNumber {
@@ -66,14 +119,6 @@ impl SnailfishNumber {
         Ok(SnailfishNumber::new(left, right))
     }
 
-    fn sum<'a>(numbers: impl IntoIterator<Item = &'a SnailfishNumber>) -> Option<SnailfishNumber> {
-        numbers
-            .into_iter()
-            .map(|it| Cow::Borrowed(it))
-            .reduce(|prev, next| Cow::Owned(prev.add_and_reduce(&next)))
-            .map(|it| it.into_owned())
-    }
-
     fn left(&self) -> &Element {
         &self.0 .0
     }
@@ -89,27 +134,43 @@ impl SnailfishNumber {
         SnailfishNumber::new(self.left().clone(), new)
     }
 
-    fn reduce(&self) -> Cow<SnailfishNumber> {
+    fn reduce_with(&self, rules: &SnailfishRules) -> Cow<'_, SnailfishNumber> {
         let mut current = Cow::Borrowed(self);
-        loop {
-            if let Some(new) = current.try_explode(0) {
-                let new = if let Element::Pair(new) = new.new {
-                    new
-                } else {
-                    panic!("Somehow exploded so hard that the top pair became a single number!")
-                };
-                current = Cow::Owned(new)
-            } else if let Some(new) = current.try_split() {
-                current = Cow::Owned(new)
-            } else {
-                break;
-            }
+        while let Some((new, _)) = current.reduce_step(rules) {
+            current = Cow::Owned(new)
         }
         current
     }
 
-    fn try_explode(&self, depth: usize) -> Option<ExplodeResult> {
-        let pair_to_explode = if depth >= 4 {
+    fn reduce_step(&self, rules: &SnailfishRules) -> Option<(SnailfishNumber, ReductionAction)> {
+        let (new, action) = if let Some(exploded) = self.try_explode(0, rules) {
+            let (left, right) = exploded.exploded;
+            let action = ReductionAction::Explode {
+                path: exploded.path,
+                left,
+                right,
+            };
+            (exploded.new, action)
+        } else if let Some(split) = self.try_split(rules) {
+            let action = ReductionAction::Split {
+                path: split.path,
+                value: split.value,
+            };
+            (split.new, action)
+        } else {
+            return None;
+        };
+
+        let new = if let Element::Pair(new) = new {
+            new
+        } else {
+            panic!("Somehow exploded so hard that the top pair became a single number!")
+        };
+        Some((new, action))
+    }
+
+    fn try_explode(&self, depth: usize, rules: &SnailfishRules) -> Option<ExplodeResult> {
+        let pair_to_explode = if depth >= rules.explode_depth {
             match (self.left(), self.right()) {
                 (Element::Regular(left), Element::Regular(right)) => Some((left, right)),
                 _ => None,
@@ -123,8 +184,11 @@ impl SnailfishNumber {
                 new: element_literal!(0),
                 left: Some(*left),
                 right: Some(*right),
+                path: vec![],
+                exploded: (*left, *right),
             })
-        } else if let Some(exploded_on_left) = self.left().try_explode(depth + 1) {
+        } else if let Some(mut exploded_on_left) = self.left().try_explode(depth + 1, rules) {
+            exploded_on_left.path.insert(0, Side::Left);
             let new_right = if let Some(new_right) = exploded_on_left.right {
                 self.right().try_receive_explosion_right(new_right)
             } else {
@@ -134,18 +198,18 @@ impl SnailfishNumber {
                 let new_number = SnailfishNumber::new(exploded_on_left.new, new_right);
                 Some(ExplodeResult {
                     new: Element::Pair(new_number),
-                    left: exploded_on_left.left,
                     right: None,
+                    ..exploded_on_left
                 })
             } else {
                 let new_number = SnailfishNumber::new(exploded_on_left.new, self.right().clone());
                 Some(ExplodeResult {
                     new: Element::Pair(new_number),
-                    left: exploded_on_left.left,
-                    right: exploded_on_left.right,
+                    ..exploded_on_left
                 })
             }
-        } else if let Some(exploded_on_right) = self.right().try_explode(depth + 1) {
+        } else if let Some(mut exploded_on_right) = self.right().try_explode(depth + 1, rules) {
+            exploded_on_right.path.insert(0, Side::Right);
             let new_left = if let Some(new_left) = exploded_on_right.left {
                 self.left().try_receive_explosion_left(new_left)
             } else {
@@ -156,14 +220,13 @@ impl SnailfishNumber {
                 Some(ExplodeResult {
                     new: Element::Pair(new_number),
                     left: None,
-                    right: exploded_on_right.right,
+                    ..exploded_on_right
                 })
             } else {
                 let new_number = SnailfishNumber::new(self.left().clone(), exploded_on_right.new);
                 Some(ExplodeResult {
                     new: Element::Pair(new_number),
-                    left: exploded_on_right.left,
-                    right: exploded_on_right.right,
+                    ..exploded_on_right
                 })
             }
         } else {
@@ -171,22 +234,75 @@ impl SnailfishNumber {
         }
     }
 
-    fn try_split(&self) -> Option<SnailfishNumber> {
-        if let Some(new_left) = self.left().try_split() {
-            Some(self.update_left(new_left))
-        } else if let Some(new_right) = self.right().try_split() {
-            Some(self.update_right(new_right))
+    fn try_split(&self, rules: &SnailfishRules) -> Option<SplitResult> {
+        if let Some(mut split_on_left) = self.left().try_split(rules) {
+            split_on_left.path.insert(0, Side::Left);
+            Some(SplitResult {
+                new: Element::Pair(self.update_left(split_on_left.new)),
+                ..split_on_left
+            })
+        } else if let Some(mut split_on_right) = self.right().try_split(rules) {
+            split_on_right.path.insert(0, Side::Right);
+            Some(SplitResult {
+                new: Element::Pair(self.update_right(split_on_right.new)),
+                ..split_on_right
+            })
         } else {
             None
         }
     }
+}
+
+/// Arithmetic that both representations of snailfish numbers support
+pub trait SnailfishMath: Sized + Clone {
+    fn add_and_reduce_with(&self, other: &Self, rules: &SnailfishRules) -> Self;
+    fn magnitude_with(&self, rules: &SnailfishRules) -> u32;
+    /// The total of all the regular numbers, ignoring structure
+    fn regular_sum(&self) -> u32;
+
+    fn magnitude(&self) -> u32 {
+        self.magnitude_with(&SnailfishRules::default())
+    }
 
-    fn add_and_reduce(&self, other: &SnailfishNumber) -> SnailfishNumber {
-        (self + other).reduce().into_owned()
+    fn sum<'a>(numbers: impl IntoIterator<Item = &'a Self>) -> Option<Self>
+    where
+        Self: 'a,
+    {
+        Self::sum_with(numbers, &SnailfishRules::default())
     }
 
-    fn magnitude(&self) -> u32 {
-        self.left().magnitude() * 3 + self.right().magnitude() * 2
+    fn sum_with<'a>(
+        numbers: impl IntoIterator<Item = &'a Self>,
+        rules: &SnailfishRules,
+    ) -> Option<Self>
+    where
+        Self: 'a,
+    {
+        numbers
+            .into_iter()
+            .map(|it| Cow::Borrowed(it))
+            .reduce(|prev, next| Cow::Owned(prev.add_and_reduce_with(&next, rules)))
+            .map(|it| it.into_owned())
+    }
+}
+
+impl SnailfishMath for SnailfishNumber {
+    fn add_and_reduce_with(
+        &self,
+        other: &SnailfishNumber,
+        rules: &SnailfishRules,
+    ) -> SnailfishNumber {
+        (self + other).reduce_with(rules).into_owned()
+    }
+
+    fn magnitude_with(&self, rules: &SnailfishRules) -> u32 {
+        let (left_weight, right_weight) = rules.magnitude_weights;
+        self.left().magnitude_with(rules) * left_weight
+            + self.right().magnitude_with(rules) * right_weight
+    }
+
+    fn regular_sum(&self) -> u32 {
+        self.left().regular_sum() + self.right().regular_sum()
     }
 }
 
@@ -239,31 +355,39 @@ impl Element {
             let snailfish_number = SnailfishNumber::parse_from_stream(stream)?;
             Ok(Element::Pair(snailfish_number))
         } else {
-            // assume only a single digit; numbers >=10 only exist during reduction
-            let digit_str = stream
-                .next()
-                .ok_or(anyhow!("End of string while parsing a number"))?;
-            let num = digit_str.to_string().parse()?;
-            Ok(Element::Regular(num))
+            // numbers >=10 only exist partway through reduction, but they can still be parsed
+            let mut digits = String::new();
+            while let Some(digit) = stream.next_if(|it| it.is_ascii_digit()) {
+                digits.push(digit);
+            }
+            if digits.is_empty() {
+                match stream.peek() {
+                    Some(c) => bail!("Expected a number, but got a '{}' instead", c),
+                    None => bail!("End of string while parsing a number"),
+                }
+            }
+            Ok(Element::Regular(digits.parse()?))
         }
     }
 
-    fn try_explode(&self, depth: usize) -> Option<ExplodeResult> {
+    fn try_explode(&self, depth: usize, rules: &SnailfishRules) -> Option<ExplodeResult> {
         match self {
-            Element::Pair(snailfish_number) => snailfish_number.try_explode(depth),
+            Element::Pair(snailfish_number) => snailfish_number.try_explode(depth, rules),
             Element::Regular(_) => None,
         }
     }
 
-    fn try_split(&self) -> Option<Element> {
+    fn try_split(&self, rules: &SnailfishRules) -> Option<SplitResult> {
         match self {
-            Element::Pair(snailfish_number) => {
-                snailfish_number.try_split().map(|it| Element::Pair(it))
-            }
-            &Element::Regular(number) if number >= 10 => {
+            Element::Pair(snailfish_number) => snailfish_number.try_split(rules),
+            &Element::Regular(number) if number >= rules.split_threshold => {
                 let left = number / 2;
                 let right = number - left;
-                Some(element_literal!([left, right]))
+                Some(SplitResult {
+                    new: element_literal!([left, right]),
+                    path: vec![],
+                    value: number,
+                })
             }
             Element::Regular(_) => None,
         }
@@ -299,10 +423,17 @@ impl Element {
         }
     }
 
-    fn magnitude(&self) -> u32 {
+    fn magnitude_with(&self, rules: &SnailfishRules) -> u32 {
+        match self {
+            Element::Pair(snailfish_num) => snailfish_num.magnitude_with(rules),
+            Element::Regular(number) => *number,
+        }
+    }
+
+    fn regular_sum(&self) -> u32 {
         match self {
-            Element::Pair(snailfish_num) => snailfish_num.magnitude(),
-            Element::Regular(number) => *number as u32,
+            Element::Pair(snailfish_num) => snailfish_num.regular_sum(),
+            Element::Regular(number) => *number,
         }
     }
 }
@@ -321,17 +452,129 @@ struct ExplodeResult {
     new: Element,
     left: Option<Digit>,
     right: Option<Digit>,
+    /// Path from the returned element down to the pair that exploded
+    path: Vec<Side>,
+    exploded: (Digit, Digit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SplitResult {
+    new: Element,
+    /// Path from the returned element down to the number that split
+    path: Vec<Side>,
+    value: Digit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
 }
 
-fn find_largest_combination(numbers: &[SnailfishNumber]) -> Option<u32> {
-    let combinations: Box<[(&SnailfishNumber, &SnailfishNumber)]> =
-        numbers.into_iter().tuple_combinations().collect();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReductionAction {
+    /// The pair at `path` exploded, carrying its values out to its neighbors
+    Explode {
+        path: Vec<Side>,
+        left: Digit,
+        right: Digit,
+    },
+    /// The regular number at `path` split into a pair
+    Split { path: Vec<Side>, value: Digit },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargestCombination<T> {
+    /// Index of the number on the left side of the addition
+    pub left: usize,
+    /// Index of the number on the right side of the addition
+    pub right: usize,
+    /// The reduced sum
+    pub result: T,
+    pub magnitude: u32,
+}
+
+pub fn find_largest_combination<T: SnailfishMath + Send + Sync>(
+    numbers: &[T],
+) -> Option<LargestCombination<T>> {
+    find_largest_combination_with(numbers, &SnailfishRules::default())
+}
 
-    combinations
+pub fn find_largest_combination_with<T: SnailfishMath + Send + Sync>(
+    numbers: &[T],
+    rules: &SnailfishRules,
+) -> Option<LargestCombination<T>> {
+    let regular_sums = numbers.iter().map(|it| it.regular_sum()).collect_vec();
+    let mut candidates = (0..numbers.len())
+        .flat_map(|left| (0..numbers.len()).map(move |right| (left, right)))
+        .filter(|(left, right)| left != right)
+        .map(|(left, right)| {
+            let bound = magnitude_upper_bound(regular_sums[left] + regular_sums[right], rules);
+            (bound, left, right)
+        })
+        .collect_vec();
+    // try the most promising additions first, so the best so far climbs quickly and prunes the rest
+    candidates.sort_unstable_by_key(|&(bound, _, _)| Reverse(bound));
+
+    let best_so_far = AtomicU32::new(0);
+    candidates
         .into_par_iter()
-        .flat_map(|&(a, b)| [(a, b), (b, a)].into_par_iter())
-        .map(|(a, b)| a.add_and_reduce(b).magnitude())
-        .max()
+        .filter_map(|(bound, left, right)| {
+            if bound < best_so_far.load(Ordering::Relaxed) {
+                return None;
+            }
+            let result = numbers[left].add_and_reduce_with(&numbers[right], rules);
+            let magnitude = result.magnitude_with(rules);
+            best_so_far.fetch_max(magnitude, Ordering::Relaxed);
+            Some(LargestCombination {
+                left,
+                right,
+                result,
+                magnitude,
+            })
+        })
+        // ties go to the lowest indices, so the answer doesn't depend on thread scheduling
+        .max_by(|a, b| {
+            a.magnitude
+                .cmp(&b.magnitude)
+                .then((b.left, b.right).cmp(&(a.left, a.right)))
+        })
+}
+
+/// The largest magnitude a fully reduced number could have if its regular numbers add up to `total`.
+/// Reduction never increases that total, so this also bounds the result of an addition.
+fn magnitude_upper_bound(total: u32, rules: &SnailfishRules) -> u32 {
+    // Every regular number in a reduced number can be pushed down to its own spot at the deepest
+    // level without losing weight, so the best case fills the heaviest of those spots first.
+    // Those are the spots reached by going toward the heavier side the most often.
+    let depth = rules.explode_depth;
+    let (left_weight, right_weight) = rules.magnitude_weights;
+    let heavy = left_weight.max(right_weight) as u64;
+    let light = left_weight.min(right_weight) as u64;
+    let max_value = rules.split_threshold.saturating_sub(1) as u64;
+
+    let mut remaining = total as u64;
+    let mut bound = 0u64;
+    // how many spots there are with this many turns toward the lighter side
+    let mut count = 1u64;
+    for light_turns in 0..=depth {
+        let weight = saturating_pow(heavy, depth - light_turns)
+            .saturating_mul(saturating_pow(light, light_turns));
+        let used = remaining.min(max_value.saturating_mul(count));
+        bound = bound.saturating_add(used.saturating_mul(weight));
+        remaining -= used;
+        if remaining == 0 {
+            break;
+        }
+        // there were fewer spots than `total`, so this can't overflow
+        count = (count as u128 * (depth - light_turns) as u128 / (light_turns as u128 + 1)) as u64;
+    }
+    bound.min(u32::MAX as u64) as u32
+}
+
+fn saturating_pow(base: u64, exponent: usize) -> u64 {
+    // anything bigger than 1 has saturated long before the exponent stops fitting in a u32
+    base.saturating_pow(exponent.min(u32::MAX as usize) as u32)
 }
 
 #[cfg(test)]
@@ -356,6 +599,16 @@ mod tests {
         .collect();
     }
 
+    /// Runs a sum through both representations
+    fn assert_sum(numbers: &[SnailfishNumber], expected: SnailfishNumber) {
+        assert_eq!(SnailfishNumber::sum(numbers.iter()), Some(expected.clone()));
+
+        let flat_numbers = numbers.iter().map(FlatSnailfishNumber::from).collect_vec();
+        let flat_sum = FlatSnailfishNumber::sum(flat_numbers.iter()).unwrap();
+        assert_eq!(SnailfishNumber::from(&flat_sum), expected);
+        assert_eq!(flat_sum.magnitude(), expected.magnitude());
+    }
+
     #[test]
     fn parse_and_display() {
         fn assert_correct_parsing(input: &str) {
@@ -372,13 +625,82 @@ mod tests {
         assert_correct_parsing("[[[[1,3],[5,3]],[[1,3],[8,7]]],[[[4,9],[6,9]],[[8,2],[7,3]]]]");
     }
 
+    #[test]
+    fn parse_multi_digit() {
+        let partially_reduced: SnailfishNumber = "[[[[0,7],4],[15,[0,13]]],[1,1]]".parse().unwrap();
+        assert_eq!(
+            partially_reduced.to_string(),
+            "[[[[0,7],4],[15,[0,13]]],[1,1]]"
+        );
+        assert_eq!(
+            partially_reduced
+                .reduce_with(&SnailfishRules::default())
+                .into_owned(),
+            snailfish_num!([[[[0, 7], 4], [[7, 8], [6, 0]]], [8, 1]])
+        );
+
+        assert!(SnailfishNumber::from_str("[1,]").is_err());
+        assert!(SnailfishNumber::from_str("[1,-2]").is_err());
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let shallow_explode = SnailfishRules::new(1, 10, (3, 2)).unwrap();
+        assert_eq!(
+            snailfish_num!([[1, 2], 3]).reduce_with(&shallow_explode),
+            Cow::Borrowed(&snailfish_num!([0, 5]))
+        );
+
+        let high_split = SnailfishRules::new(4, 20, (3, 2)).unwrap();
+        assert_eq!(
+            snailfish_num!([19, 25]).reduce_with(&high_split),
+            Cow::Borrowed(&snailfish_num!([19, [12, 13]]))
+        );
+
+        let even_weights = SnailfishRules::new(4, 10, (1, 1)).unwrap();
+        assert_eq!(snailfish_num!([[1, 2], 3]).magnitude_with(&even_weights), 6);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(SnailfishRules::new(0, 10, (3, 2)).is_err());
+        assert!(SnailfishRules::new(4, 1, (3, 2)).is_err());
+        assert!(SnailfishRules::new(4, 0, (3, 2)).is_err());
+        assert_eq!(
+            SnailfishRules::new(4, 10, (3, 2)).ok(),
+            Some(SnailfishRules::default())
+        );
+        assert!(SnailfishRules::new(1, 2, (3, 2)).is_ok());
+    }
+
+    #[test]
+    fn test_custom_rules_flat() {
+        let rules = SnailfishRules::new(3, 12, (2, 3)).unwrap();
+        let sum = SnailfishNumber::sum_with(EXAMPLE_INPUT.iter(), &rules).unwrap();
+
+        let flat_input = EXAMPLE_INPUT
+            .iter()
+            .map(FlatSnailfishNumber::from)
+            .collect_vec();
+        let flat_sum = FlatSnailfishNumber::sum_with(flat_input.iter(), &rules).unwrap();
+        assert_eq!(SnailfishNumber::from(&flat_sum), sum);
+        assert_eq!(flat_sum.magnitude_with(&rules), sum.magnitude_with(&rules));
+    }
+
     #[test]
     fn test_single_explode() {
         fn assert_explode(input: SnailfishNumber, expected: SnailfishNumber) {
             assert_eq!(
-                input.try_explode(0).unwrap().new,
+                input
+                    .try_explode(0, &SnailfishRules::default())
+                    .unwrap()
+                    .new,
                 Element::Pair(expected.clone())
-            )
+            );
+
+            let mut flat = FlatSnailfishNumber::from(&input);
+            assert!(flat.try_explode(&SnailfishRules::default()));
+            assert_eq!(SnailfishNumber::from(&flat), expected);
         }
         assert_explode(
             snailfish_num!([[[[[9, 8], 1], 2], 3], 4]),
@@ -406,78 +728,72 @@ mod tests {
     fn test_reduce() {
         let start = snailfish_num!([[[[[4, 3], 4], 4], [7, [[8, 4], 9]]], [1, 1]]);
         let expected = snailfish_num!([[[[0, 7], 4], [[7, 8], [6, 0]]], [8, 1]]);
-        assert_eq!(start.reduce(), Cow::Borrowed(&expected));
+        assert_eq!(
+            start.reduce_with(&SnailfishRules::default()),
+            Cow::Borrowed(&expected)
+        );
+
+        let mut flat = FlatSnailfishNumber::from(&start);
+        flat.reduce(&SnailfishRules::default());
+        assert_eq!(SnailfishNumber::from(&flat), expected);
     }
 
     #[test]
-
     fn test_sum() {
-        assert_eq!(
-            SnailfishNumber::sum(
-                [
-                    snailfish_num!([1, 1]),
-                    snailfish_num!([2, 2]),
-                    snailfish_num!([3, 3]),
-                    snailfish_num!([4, 4]),
-                ]
-                .iter(),
-            ),
-            Some(snailfish_num!([[[[1, 1], [2, 2]], [3, 3]], [4, 4]])),
+        assert_sum(
+            &[
+                snailfish_num!([1, 1]),
+                snailfish_num!([2, 2]),
+                snailfish_num!([3, 3]),
+                snailfish_num!([4, 4]),
+            ],
+            snailfish_num!([[[[1, 1], [2, 2]], [3, 3]], [4, 4]]),
         );
-        assert_eq!(
-            SnailfishNumber::sum(
-                [
-                    snailfish_num!([1, 1]),
-                    snailfish_num!([2, 2]),
-                    snailfish_num!([3, 3]),
-                    snailfish_num!([4, 4]),
-                    snailfish_num!([5, 5]),
-                ]
-                .iter(),
-            ),
-            Some(snailfish_num!([[[[3, 0], [5, 3]], [4, 4]], [5, 5]])),
+        assert_sum(
+            &[
+                snailfish_num!([1, 1]),
+                snailfish_num!([2, 2]),
+                snailfish_num!([3, 3]),
+                snailfish_num!([4, 4]),
+                snailfish_num!([5, 5]),
+            ],
+            snailfish_num!([[[[3, 0], [5, 3]], [4, 4]], [5, 5]]),
         );
-        assert_eq!(
-            SnailfishNumber::sum(
-                [
-                    snailfish_num!([1, 1]),
-                    snailfish_num!([2, 2]),
-                    snailfish_num!([3, 3]),
-                    snailfish_num!([4, 4]),
-                    snailfish_num!([5, 5]),
-                    snailfish_num!([6, 6]),
-                ]
-                .iter()
-            ),
-            Some(snailfish_num!([[[[5, 0], [7, 4]], [5, 5]], [6, 6]])),
+        assert_sum(
+            &[
+                snailfish_num!([1, 1]),
+                snailfish_num!([2, 2]),
+                snailfish_num!([3, 3]),
+                snailfish_num!([4, 4]),
+                snailfish_num!([5, 5]),
+                snailfish_num!([6, 6]),
+            ],
+            snailfish_num!([[[[5, 0], [7, 4]], [5, 5]], [6, 6]]),
         );
     }
 
     #[test]
     fn test_sum_large() {
-        assert_eq!(
-            SnailfishNumber::sum(
-                [
-                    snailfish_num!([[[0, [4, 5]], [0, 0]], [[[4, 5], [2, 6]], [9, 5]]]),
-                    snailfish_num!([7, [[[3, 7], [4, 3]], [[6, 3], [8, 8]]]]),
-                    snailfish_num!([[2, [[0, 8], [3, 4]]], [[[6, 7], 1], [7, [1, 6]]]]),
-                    snailfish_num!([
-                        [[[2, 4], 7], [6, [0, 5]]],
-                        [[[6, 8], [2, 8]], [[2, 1], [4, 5]]]
-                    ]),
-                    snailfish_num!([7, [5, [[3, 8], [1, 4]]]]),
-                    snailfish_num!([[2, [2, 2]], [8, [8, 1]]]),
-                    snailfish_num!([2, 9]),
-                    snailfish_num!([1, [[[9, 3], 9], [[9, 0], [0, 7]]]]),
-                    snailfish_num!([[[5, [7, 4]], 7], 1]),
-                    snailfish_num!([[[[4, 2], 2], 6], [8, 7]]),
-                ]
-                .iter()
-            ),
-            Some(snailfish_num!([
+        assert_sum(
+            &[
+                snailfish_num!([[[0, [4, 5]], [0, 0]], [[[4, 5], [2, 6]], [9, 5]]]),
+                snailfish_num!([7, [[[3, 7], [4, 3]], [[6, 3], [8, 8]]]]),
+                snailfish_num!([[2, [[0, 8], [3, 4]]], [[[6, 7], 1], [7, [1, 6]]]]),
+                snailfish_num!([
+                    [[[2, 4], 7], [6, [0, 5]]],
+                    [[[6, 8], [2, 8]], [[2, 1], [4, 5]]]
+                ]),
+                snailfish_num!([7, [5, [[3, 8], [1, 4]]]]),
+                snailfish_num!([[2, [2, 2]], [8, [8, 1]]]),
+                snailfish_num!([2, 9]),
+                snailfish_num!([1, [[[9, 3], 9], [[9, 0], [0, 7]]]]),
+                snailfish_num!([[[5, [7, 4]], 7], 1]),
+                snailfish_num!([[[[4, 2], 2], 6], [8, 7]]),
+            ],
+            snailfish_num!([
                 [[[8, 7], [7, 7]], [[8, 6], [7, 7]]],
                 [[[0, 7], [6, 6]], [8, 7]]
-            ])),
+            ]),
         );
     }
 
@@ -523,6 +839,13 @@ mod tests {
             ]))
         );
         assert_eq!(sum.unwrap().magnitude(), 4140);
+
+        let flat_input = EXAMPLE_INPUT
+            .iter()
+            .map(FlatSnailfishNumber::from)
+            .collect_vec();
+        let flat_sum = FlatSnailfishNumber::sum(flat_input.iter()).unwrap();
+        assert_eq!(flat_sum.magnitude(), 4140);
     }
 
     #[test]
@@ -533,8 +856,49 @@ mod tests {
 
     #[test]
     fn test_find_largest_combination() {
+        let expected_result: SnailfishNumber =
+            "[[[[7,8],[6,6]],[[6,0],[7,7]]],[[[7,8],[8,8]],[[7,9],[0,6]]]]"
+                .parse()
+                .unwrap();
         let result = find_largest_combination(&EXAMPLE_INPUT);
-        assert_eq!(result, Some(3993))
+        assert_eq!(
+            result,
+            Some(LargestCombination {
+                left: 8,
+                right: 0,
+                result: expected_result.clone(),
+                magnitude: 3993
+            })
+        );
+
+        let flat_input = EXAMPLE_INPUT
+            .iter()
+            .map(FlatSnailfishNumber::from)
+            .collect_vec();
+        let result = find_largest_combination(&flat_input).unwrap();
+        assert_eq!((result.left, result.right), (8, 0));
+        assert_eq!(SnailfishNumber::from(&result.result), expected_result);
+        assert_eq!(result.magnitude, 3993);
+    }
+
+    #[test]
+    fn test_magnitude_upper_bound() {
+        let rules = SnailfishRules::default();
+        // sixteen 9s at the deepest level
+        assert_eq!(magnitude_upper_bound(1000, &rules), 5625);
+        assert_eq!(magnitude_upper_bound(9, &rules), 9 * 81);
+        assert_eq!(magnitude_upper_bound(10, &rules), 9 * 81 + 54);
+        let deep = SnailfishRules::new(usize::MAX, 10, (3, 2)).unwrap();
+        assert_eq!(magnitude_upper_bound(1000, &deep), u32::MAX);
+        assert_eq!(magnitude_upper_bound(0, &deep), 0);
+        let unweighted = SnailfishRules::new(100, 10, (1, 1)).unwrap();
+        assert_eq!(magnitude_upper_bound(1000, &unweighted), 1000);
+
+        for (a, b) in EXAMPLE_INPUT.iter().tuple_combinations() {
+            let bound = magnitude_upper_bound(a.regular_sum() + b.regular_sum(), &rules);
+            assert!(a.add_and_reduce_with(b, &rules).magnitude() <= bound);
+            assert!(b.add_and_reduce_with(a, &rules).magnitude() <= bound);
+        }
     }
 
     #[test]
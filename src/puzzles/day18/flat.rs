@@ -0,0 +1,204 @@
+use super::{Digit, Element, SnailfishMath, SnailfishNumber, SnailfishRules};
+use std::fmt::{Debug, Display};
+
+/// A snailfish number stored as its regular numbers from left to right,
+/// each tagged with how many pairs it's nested inside.
+/// Exploding and splitting edit the list in place instead of rebuilding pairs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FlatSnailfishNumber {
+    elements: Vec<FlatElement>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FlatElement {
+    value: Digit,
+    depth: u8,
+}
+
+impl FlatSnailfishNumber {
+    pub fn reduce(&mut self, rules: &SnailfishRules) {
+        while self.try_explode(rules) || self.try_split(rules) {}
+    }
+
+    pub fn try_explode(&mut self, rules: &SnailfishRules) -> bool {
+        // A regular number nested inside N + 1 pairs belongs to a pair nested inside N.
+        // The leftmost neighbors that are that deep and equally deep are always a pair:
+        // if they weren't, the left one's sibling would hold a deeper pair further left.
+        let index = self.elements.windows(2).position(|window| {
+            window[0].depth as usize > rules.explode_depth && window[0].depth == window[1].depth
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let left = self.elements[index];
+        let right = self.elements.remove(index + 1);
+        if index > 0 {
+            self.elements[index - 1].value += left.value;
+        }
+        if let Some(next) = self.elements.get_mut(index + 1) {
+            next.value += right.value;
+        }
+        self.elements[index] = FlatElement {
+            value: 0,
+            depth: left.depth - 1,
+        };
+        true
+    }
+
+    pub fn try_split(&mut self, rules: &SnailfishRules) -> bool {
+        let index = match self
+            .elements
+            .iter()
+            .position(|it| it.value >= rules.split_threshold)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let FlatElement { value, depth } = self.elements[index];
+        let left = value / 2;
+        self.elements[index] = FlatElement {
+            value: left,
+            depth: depth + 1,
+        };
+        self.elements.insert(
+            index + 1,
+            FlatElement {
+                value: value - left,
+                depth: depth + 1,
+            },
+        );
+        true
+    }
+
+    /// Rebuilds the pair structure by merging equally deep neighbors, innermost first
+    fn fold_pairs<T>(&self, leaf: impl Fn(Digit) -> T, pair: impl Fn(T, T) -> T) -> T {
+        let mut stack: Vec<(T, u8)> = Vec::with_capacity(self.elements.len());
+        for element in self.elements.iter() {
+            let mut current = (leaf(element.value), element.depth);
+            while let Some((_, depth)) = stack.last() {
+                if *depth != current.1 {
+                    break;
+                }
+                let (left, depth) = stack.pop().unwrap();
+                current = (pair(left, current.0), depth - 1);
+            }
+            stack.push(current);
+        }
+        let (result, _) = stack.pop().expect("Snailfish numbers can't be empty");
+        debug_assert!(stack.is_empty(), "Flat snailfish number wasn't a tree");
+        result
+    }
+}
+
+impl SnailfishMath for FlatSnailfishNumber {
+    fn add_and_reduce_with(
+        &self,
+        other: &FlatSnailfishNumber,
+        rules: &SnailfishRules,
+    ) -> FlatSnailfishNumber {
+        let mut elements = Vec::with_capacity(self.elements.len() + other.elements.len());
+        elements.extend(
+            self.elements
+                .iter()
+                .chain(other.elements.iter())
+                .map(|it| FlatElement {
+                    value: it.value,
+                    depth: it.depth + 1,
+                }),
+        );
+        let mut result = FlatSnailfishNumber { elements };
+        result.reduce(rules);
+        result
+    }
+
+    fn magnitude_with(&self, rules: &SnailfishRules) -> u32 {
+        let (left_weight, right_weight) = rules.magnitude_weights;
+        self.fold_pairs(
+            |value| value,
+            |left, right| left * left_weight + right * right_weight,
+        )
+    }
+
+    fn regular_sum(&self) -> u32 {
+        self.elements.iter().map(|it| it.value).sum()
+    }
+}
+
+impl From<&SnailfishNumber> for FlatSnailfishNumber {
+    fn from(number: &SnailfishNumber) -> Self {
+        fn flatten(element: &Element, depth: u8, elements: &mut Vec<FlatElement>) {
+            match element {
+                Element::Pair(pair) => {
+                    flatten(pair.left(), depth + 1, elements);
+                    flatten(pair.right(), depth + 1, elements);
+                }
+                &Element::Regular(value) => elements.push(FlatElement { value, depth }),
+            }
+        }
+
+        let mut elements = vec![];
+        flatten(number.left(), 1, &mut elements);
+        flatten(number.right(), 1, &mut elements);
+        FlatSnailfishNumber { elements }
+    }
+}
+
+impl From<&FlatSnailfishNumber> for SnailfishNumber {
+    fn from(number: &FlatSnailfishNumber) -> Self {
+        match number.fold_pairs(Element::Regular, Element::new_pair) {
+            Element::Pair(result) => result,
+            Element::Regular(_) => panic!("Flat snailfish number was a single regular number"),
+        }
+    }
+}
+
+impl Display for FlatSnailfishNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", SnailfishNumber::from(self))
+    }
+}
+
+impl Debug for FlatSnailfishNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_string().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for input in [
+            "[1,2]",
+            "[[1,9],[8,5]]",
+            "[[[9,[3,8]],[[0,9],6]],[[[3,7],[4,9]],3]]",
+            "[[[[1,3],[5,3]],[[1,3],[8,7]]],[[[4,9],[6,9]],[[8,2],[7,3]]]]",
+        ] {
+            let number: SnailfishNumber = input.parse().unwrap();
+            let flat = FlatSnailfishNumber::from(&number);
+            assert_eq!(SnailfishNumber::from(&flat), number);
+            assert_eq!(flat.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_explode_skips_unbalanced_pair() {
+        // [5,[6,7]] is nested 4 deep but isn't a pair of regular numbers, so [6,7] explodes
+        let number: SnailfishNumber = "[[[[[5,[6,7]],1],2],3],4]".parse().unwrap();
+        let mut flat = FlatSnailfishNumber::from(&number);
+        assert!(flat.try_explode(&SnailfishRules::default()));
+        assert_eq!(flat.to_string(), "[[[[[11,0],8],2],3],4]");
+        assert_eq!(
+            number
+                .try_explode(0, &SnailfishRules::default())
+                .unwrap()
+                .new,
+            Element::Pair(SnailfishNumber::from(&flat))
+        );
+    }
+}
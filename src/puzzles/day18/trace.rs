@@ -0,0 +1,127 @@
+use super::{ReductionAction, SnailfishNumber, SnailfishRules};
+use std::fmt::Display;
+
+impl SnailfishNumber {
+    /// Like `reduce`, but keeps a record of every explode and split along the way
+    pub fn reduce_with_trace(&self, rules: &SnailfishRules) -> ReductionTrace {
+        let mut steps = vec![];
+        let mut current = self.clone();
+        while let Some((new, action)) = current.reduce_step(rules) {
+            steps.push(ReductionStep {
+                action,
+                result: new.clone(),
+            });
+            current = new;
+        }
+        ReductionTrace {
+            start: self.clone(),
+            steps,
+        }
+    }
+
+    pub fn add_with_trace(
+        &self,
+        other: &SnailfishNumber,
+        rules: &SnailfishRules,
+    ) -> ReductionTrace {
+        (self + other).reduce_with_trace(rules)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionStep {
+    pub action: ReductionAction,
+    pub result: SnailfishNumber,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionTrace {
+    pub start: SnailfishNumber,
+    pub steps: Vec<ReductionStep>,
+}
+
+impl ReductionTrace {
+    pub fn actions(&self) -> impl Iterator<Item = &ReductionAction> {
+        self.steps.iter().map(|it| &it.action)
+    }
+
+    pub fn result(&self) -> &SnailfishNumber {
+        self.steps
+            .last()
+            .map(|it| &it.result)
+            .unwrap_or(&self.start)
+    }
+}
+
+/// Formats the trace like the puzzle's walkthrough of an addition
+impl Display for ReductionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:16}{}", "after addition:", self.start)?;
+        for step in self.steps.iter() {
+            let label = match step.action {
+                ReductionAction::Explode { .. } => "after explode:",
+                ReductionAction::Split { .. } => "after split:",
+            };
+            write!(f, "\n{:16}{}", label, step.result)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Side::{Left as L, Right as R};
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_reduce_with_trace() {
+        let a: SnailfishNumber = "[[[[4,3],4],4],[7,[[8,4],9]]]".parse().unwrap();
+        let b: SnailfishNumber = "[1,1]".parse().unwrap();
+        let trace = a.add_with_trace(&b, &SnailfishRules::default());
+        assert_eq!(
+            trace.actions().cloned().collect_vec(),
+            vec![
+                ReductionAction::Explode {
+                    path: vec![L, L, L, L],
+                    left: 4,
+                    right: 3
+                },
+                ReductionAction::Explode {
+                    path: vec![L, R, R, L],
+                    left: 8,
+                    right: 4
+                },
+                ReductionAction::Split {
+                    path: vec![L, R, L],
+                    value: 15
+                },
+                ReductionAction::Split {
+                    path: vec![L, R, R, R],
+                    value: 13
+                },
+                ReductionAction::Explode {
+                    path: vec![L, R, R, R],
+                    left: 6,
+                    right: 7
+                },
+            ]
+        );
+        assert_eq!(
+            trace.to_string(),
+            [
+                "after addition: [[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[15,[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            trace.result().to_string(),
+            "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
+
+/// A number that's only ever added and multiplied with checked arithmetic,
+/// so that fixed-width types report overflow instead of wrapping
+pub trait CheckedNumber: Sized + Clone + Zero + One + CheckedAdd + CheckedMul {}
+
+impl<N: Clone + Zero + One + CheckedAdd + CheckedMul> CheckedNumber for N {}
+
+/// A square matrix of `CheckedNumber`s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix<N>(pub Vec<Vec<N>>);
+
+impl<N: CheckedNumber> Matrix<N> {
+    pub fn identity(size: usize) -> Self {
+        Matrix(
+            (0..size)
+                .map(|row| {
+                    (0..size)
+                        .map(|column| if row == column { N::one() } else { N::zero() })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn checked_mul(&self, other: &Matrix<N>) -> Option<Matrix<N>> {
+        let size = self.0.len();
+        let mut result = vec![vec![N::zero(); size]; size];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (k, left) in self.0[row].iter().enumerate() {
+                if left.is_zero() {
+                    continue;
+                }
+                for (column, cell) in result_row.iter_mut().enumerate() {
+                    *cell = cell.checked_add(&left.checked_mul(&other.0[k][column])?)?;
+                }
+            }
+        }
+        Some(Matrix(result))
+    }
+
+    pub fn checked_apply(&self, vector: &[N]) -> Option<Vec<N>> {
+        self.0
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector)
+                    .try_fold(N::zero(), |sum, (a, b)| sum.checked_add(&a.checked_mul(b)?))
+            })
+            .collect()
+    }
+
+    /// Raises the matrix to a power by repeated squaring
+    pub fn checked_pow(&self, exponent: u64) -> Option<Matrix<N>> {
+        let mut result = Matrix::identity(self.0.len());
+        let mut power = self.clone();
+        let mut remaining = exponent;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result.checked_mul(&power)?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                power = power.checked_mul(&power)?;
+            }
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pow() {
+        // the Fibonacci numbers
+        let matrix = Matrix(vec![vec![1u64, 1], vec![1, 0]]);
+        assert_eq!(matrix.checked_pow(0), Some(Matrix::identity(2)));
+        assert_eq!(matrix.checked_pow(10).unwrap().0[0][1], 55);
+        assert_eq!(matrix.checked_pow(100), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let matrix = Matrix(vec![vec![1u8, 2], vec![0, 3]]);
+        assert_eq!(matrix.checked_apply(&[4, 5]), Some(vec![14, 15]));
+        assert_eq!(matrix.checked_apply(&[100, 100]), None);
+    }
+}